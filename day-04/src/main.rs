@@ -1,6 +1,42 @@
 use itertools::Itertools;
 use std::collections::LinkedList;
-use std::fs;
+use std::time::Instant;
+use std::{env, fs};
+
+struct ByteGrid {
+    cells: Vec<u8>,
+    width: usize,
+    height: usize,
+}
+
+impl ByteGrid {
+    fn from(input: &str) -> ByteGrid {
+        let lines = input.trim().lines().collect_vec();
+        let height = lines.len();
+        let width = lines.first().map_or(0, |line| line.len());
+
+        let mut cells = Vec::with_capacity(width * height);
+        for line in lines {
+            cells.extend_from_slice(line.as_bytes());
+        }
+
+        ByteGrid {
+            cells,
+            width,
+            height,
+        }
+    }
+
+    fn row(&self, i: usize) -> &[u8] {
+        &self.cells[i * self.width..(i + 1) * self.width]
+    }
+
+    fn to_chars(&self) -> Vec<Vec<char>> {
+        (0..self.height)
+            .map(|i| self.row(i).iter().map(|b| *b as char).collect())
+            .collect()
+    }
+}
 
 fn solve1(grid: &[Vec<char>]) -> usize {
     #[derive(Debug)]
@@ -74,6 +110,67 @@ fn solve1(grid: &[Vec<char>]) -> usize {
     solutions.len()
 }
 
+fn count_xmas(a: &[u8], b: &[u8], c: &[u8], d: &[u8]) -> usize {
+    // Branchless so that the compiler can vectorise the comparisons
+    a.iter()
+        .zip(b)
+        .zip(c)
+        .zip(d)
+        .map(|(((a, b), c), d)| {
+            let forward = (*a == b'X') & (*b == b'M') & (*c == b'A') & (*d == b'S');
+            let backward = (*a == b'S') & (*b == b'A') & (*c == b'M') & (*d == b'X');
+            forward as usize + backward as usize
+        })
+        .sum()
+}
+
+fn solve1_fast(grid: &ByteGrid) -> usize {
+    let (height, width) = (grid.height, grid.width);
+
+    let mut num_xmas = 0;
+
+    // Each word is counted along 4 lines, both forwards and backwards
+    if width >= 4 {
+        for i in 0..height {
+            let row = grid.row(i);
+            let n = width - 3;
+            num_xmas += count_xmas(&row[..n], &row[1..n + 1], &row[2..n + 2], &row[3..]);
+        }
+    }
+
+    if height >= 4 {
+        for i in 0..height - 3 {
+            let (a, b, c, d) = (
+                grid.row(i),
+                grid.row(i + 1),
+                grid.row(i + 2),
+                grid.row(i + 3),
+            );
+
+            // Vertical
+            num_xmas += count_xmas(a, b, c, d);
+
+            if width >= 4 {
+                let n = width - 3;
+
+                // Diagonal: a . . .
+                //           . b . .
+                //           . . c .
+                //           . . . d
+                num_xmas += count_xmas(&a[..n], &b[1..n + 1], &c[2..n + 2], &d[3..]);
+
+                // Anti-diagonal: . . . a
+                //                . . b .
+                //                . c . .
+                //                d . . .
+                num_xmas += count_xmas(&a[3..], &b[2..n + 2], &c[1..n + 1], &d[..n]);
+            }
+        }
+    }
+
+    num_xmas
+}
+
 fn solve2(grid: &[Vec<char>]) -> usize {
     let height = grid.len();
     let width = grid[0].len();
@@ -108,17 +205,61 @@ fn solve2(grid: &[Vec<char>]) -> usize {
     num_xmas
 }
 
+fn generate_grid(size: usize, seed: u64) -> String {
+    let mut state = seed.max(1);
+    let mut contents = String::with_capacity(size * (size + 1));
+
+    for _ in 0..size {
+        for _ in 0..size {
+            // xorshift64
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            contents.push(['X', 'M', 'A', 'S'][(state % 4) as usize]);
+        }
+        contents.push('\n');
+    }
+
+    contents
+}
+
+fn bench(sizes: &[usize]) {
+    for size in sizes {
+        let contents = generate_grid(*size, *size as u64);
+        let bytes = ByteGrid::from(&contents);
+        let grid = bytes.to_chars();
+
+        let start = Instant::now();
+        let expected = solve1(&grid);
+        let bfs = start.elapsed();
+
+        let start = Instant::now();
+        let answer = solve1_fast(&bytes);
+        let fast = start.elapsed();
+
+        assert_eq!(expected, answer, "Fast path disagrees with BFS");
+        println!("{size}x{size}: {answer} words, bfs {bfs:?}, fast {fast:?}");
+    }
+}
+
 fn main() {
+    let args = env::args().collect_vec();
+    if args.get(1).map(String::as_str) == Some("bench") {
+        let sizes = match args.get(2) {
+            Some(size) => vec![size.parse().expect("Grid size must be a number")],
+            None => vec![140, 500, 1000, 2000],
+        };
+        bench(&sizes);
+        return;
+    }
+
     let filename = "input.txt";
     let contents = fs::read_to_string(filename).expect("Unable to read input file");
 
-    let mut grid: Vec<Vec<char>> = vec![];
-
-    for line in contents.trim().split("\n") {
-        grid.push(line.chars().collect::<Vec<char>>());
-    }
+    let bytes = ByteGrid::from(&contents);
+    let grid = bytes.to_chars();
 
-    let answer = solve1(&grid);
+    let answer = solve1_fast(&bytes);
     println!("Answer is {}", answer);
 
     let answer = solve2(&grid);
@@ -1,44 +1,163 @@
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
-use std::fs;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
 use std::iter::FromIterator;
+use std::time::Instant;
+use std::{env, fs, io};
 
 type Rule = (usize, usize);
 type Sequence = Vec<usize>;
 type Requirements = HashMap<usize, HashSet<usize>>;
 
-fn parse_rule(rule: &str) -> Rule {
-    rule.split("|")
-        .map(|page| page.parse::<usize>().expect("Unable to parse page number"))
-        .collect_tuple::<Rule>()
-        .expect("Rules must be valid")
+#[derive(Debug)]
+enum ParseError {
+    MissingSeparator,
+    InvalidRule {
+        line: usize,
+        text: String,
+    },
+    SelfRule {
+        line: usize,
+        page: usize,
+    },
+    InvalidPage {
+        line: usize,
+        column: usize,
+        text: String,
+    },
+    DuplicatePage {
+        line: usize,
+        column: usize,
+        page: usize,
+    },
+    EvenLength {
+        line: usize,
+        len: usize,
+    },
+    UnexpectedBlankLine {
+        line: usize,
+    },
 }
 
-fn parse_seq(seq: &str) -> Sequence {
-    seq.split(",")
-        .map(|page| page.parse::<usize>().expect("Unable to parse page number"))
-        .collect_vec()
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingSeparator => {
+                write!(f, "Missing blank line between rules and updates")
+            }
+            ParseError::InvalidRule { line, text } => {
+                write!(f, "Line {line}: expected rule X|Y, found {text:?}")
+            }
+            ParseError::SelfRule { line, page } => {
+                write!(
+                    f,
+                    "Line {line}: page {page} cannot be required before itself"
+                )
+            }
+            ParseError::InvalidPage { line, column, text } => {
+                write!(
+                    f,
+                    "Line {line}, column {column}: invalid page number {text:?}"
+                )
+            }
+            ParseError::DuplicatePage { line, column, page } => {
+                write!(f, "Line {line}, column {column}: page {page} appears twice")
+            }
+            ParseError::EvenLength { line, len } => {
+                write!(f, "Line {line}: update has {len} pages and no middle page")
+            }
+            ParseError::UnexpectedBlankLine { line } => {
+                write!(f, "Line {line}: unexpected blank line among updates")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum MiddlePage {
+    Strict,
+    Lower,
+    Upper,
 }
 
-fn parse_input(input: &str) -> (Vec<Rule>, Vec<Sequence>) {
+impl MiddlePage {
+    fn of(&self, seq: &Sequence) -> usize {
+        match self {
+            MiddlePage::Strict | MiddlePage::Upper => seq[seq.len() / 2],
+            MiddlePage::Lower => seq[(seq.len() - 1) / 2],
+        }
+    }
+}
+
+fn parse_page(text: &str, line: usize, column: usize) -> Result<usize, ParseError> {
+    text.parse::<usize>().map_err(|_| ParseError::InvalidPage {
+        line,
+        column,
+        text: text.to_string(),
+    })
+}
+
+fn parse_rule(rule: &str, line: usize) -> Result<Rule, ParseError> {
+    let invalid_rule = || ParseError::InvalidRule {
+        line,
+        text: rule.to_string(),
+    };
+
+    let (prereq, page) = rule.split_once("|").ok_or_else(invalid_rule)?;
+    let prereq = parse_page(prereq, line, 1)?;
+    let page = parse_page(page, line, rule.find("|").unwrap_or_default() + 2)?;
+
+    if prereq == page {
+        return Err(ParseError::SelfRule { line, page });
+    }
+
+    Ok((prereq, page))
+}
+
+fn parse_seq(seq: &str, line: usize, middle: MiddlePage) -> Result<Sequence, ParseError> {
+    let mut pages = Sequence::new();
+    let mut seen = HashSet::new();
+
+    let mut column = 1;
+    for text in seq.split(",") {
+        let page = parse_page(text, line, column)?;
+        if !seen.insert(page) {
+            return Err(ParseError::DuplicatePage { line, column, page });
+        }
+        pages.push(page);
+        column += text.len() + 1;
+    }
+
+    if pages.len().is_multiple_of(2) && matches!(middle, MiddlePage::Strict) {
+        return Err(ParseError::EvenLength {
+            line,
+            len: pages.len(),
+        });
+    }
+
+    Ok(pages)
+}
+
+fn parse_input(input: &str, middle: MiddlePage) -> Result<(Vec<Rule>, Vec<Sequence>), ParseError> {
     let mut rules: Vec<Rule> = vec![];
     let mut seqs: Vec<Sequence> = vec![];
 
     let mut reading_rules = true;
-    for line in input.trim().split("\n") {
+    for (i, line) in input.trim_end().lines().enumerate() {
+        let line_number = i + 1;
         match line {
-            "" => reading_rules = false,
-            rule if reading_rules => {
-                rules.push(parse_rule(rule));
-            }
-            seq if !reading_rules => {
-                seqs.push(parse_seq(seq));
-            }
-            _ => (),
+            "" if reading_rules => reading_rules = false,
+            "" => return Err(ParseError::UnexpectedBlankLine { line: line_number }),
+            rule if reading_rules => rules.push(parse_rule(rule, line_number)?),
+            seq => seqs.push(parse_seq(seq, line_number, middle)?),
         }
     }
 
-    (rules, seqs)
+    if reading_rules {
+        return Err(ParseError::MissingSeparator);
+    }
+
+    Ok((rules, seqs))
 }
 
 fn build_requirements(rules: &[Rule]) -> Requirements {
@@ -58,7 +177,25 @@ fn build_requirements(rules: &[Rule]) -> Requirements {
     requirements
 }
 
+type Positions = HashMap<usize, usize>;
+
+fn index_positions(seq: &Sequence) -> Positions {
+    seq.iter().enumerate().map(|(i, p)| (*p, i)).collect()
+}
+
 fn is_valid(seq: &Sequence, requirements: &Requirements) -> bool {
+    let position = index_positions(seq);
+
+    seq.iter().enumerate().all(|(i, page)| {
+        requirements.get(page).is_none_or(|prereqs| {
+            prereqs
+                .iter()
+                .all(|prereq| position.get(prereq).is_none_or(|j| *j < i))
+        })
+    })
+}
+
+fn is_valid_naive(seq: &Sequence, requirements: &Requirements) -> bool {
     let mut available = HashSet::new();
 
     for page in seq {
@@ -75,69 +212,545 @@ fn is_valid(seq: &Sequence, requirements: &Requirements) -> bool {
     true
 }
 
-fn order(seq: &Sequence, requirements: &Requirements) -> Sequence {
-    let mut res = seq.clone();
+#[derive(Debug)]
+struct Violation {
+    rule: Rule,
+    prereq_position: usize,
+    page_position: usize,
+}
+
+impl Violation {
+    fn to_json(&self) -> String {
+        let (prereq, page) = self.rule;
+        format!(
+            "{{\"rule\":\"{prereq}|{page}\",\"before\":{{\"page\":{prereq},\"position\":{}}},\"after\":{{\"page\":{page},\"position\":{}}}}}",
+            self.prereq_position, self.page_position
+        )
+    }
+}
 
-    while !is_valid(&res, requirements) {
-        let mut available = HashSet::new();
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (prereq, page) = self.rule;
+        write!(
+            f,
+            "{prereq}|{page}: page {prereq} at position {} must come before page {page} at position {}",
+            self.prereq_position, self.page_position
+        )
+    }
+}
 
-        let mut i = 0usize;
-        while i < res.len() {
-            let page = res[i];
+fn find_violations(seq: &Sequence, requirements: &Requirements) -> Vec<Violation> {
+    let position = index_positions(seq);
 
-            let mut swapped = false;
+    let mut violations = vec![];
+    for (page_position, page) in seq.iter().enumerate() {
+        let Some(prereqs) = requirements.get(page) else {
+            continue;
+        };
 
-            if let Some(prereqs) = requirements.get(&page) {
-                let seq_prereqs =
-                    HashSet::from_iter(prereqs.iter().filter(|p| seq.contains(p)).copied());
-                let mut unsatisfied_reqs = &seq_prereqs - &available;
+        for prereq in prereqs.iter().sorted() {
+            match position.get(prereq) {
+                Some(prereq_position) if *prereq_position > page_position => {
+                    violations.push(Violation {
+                        rule: (*prereq, *page),
+                        prereq_position: *prereq_position,
+                        page_position,
+                    });
+                }
+                _ => (),
+            }
+        }
+    }
 
-                let mut j = i + 1;
-                while !unsatisfied_reqs.is_empty() && j < res.len() {
-                    unsatisfied_reqs.remove(&res[j]);
+    violations
+}
 
-                    if unsatisfied_reqs.is_empty() {
-                        res.remove(i);
-                        res.insert(j, page);
-                        swapped = true;
-                    }
+enum ReportFormat {
+    Text,
+    Json,
+}
+
+fn report(seqs: &[Sequence], requirements: &Requirements, format: ReportFormat) -> String {
+    let rejected = seqs
+        .iter()
+        .enumerate()
+        .map(|(i, seq)| (i, seq, find_violations(seq, requirements)))
+        .filter(|(_, _, violations)| !violations.is_empty());
+
+    match format {
+        ReportFormat::Text => rejected
+            .map(|(i, seq, violations)| {
+                let lines = violations.iter().map(|v| format!("  {v}")).join("\n");
+                format!("Update {i} ({}) violates:\n{lines}", seq.iter().join(","))
+            })
+            .join("\n"),
+        ReportFormat::Json => {
+            let entries = rejected
+                .map(|(i, seq, violations)| {
+                    format!(
+                        "{{\"update\":{i},\"pages\":[{}],\"violations\":[{}]}}",
+                        seq.iter().join(","),
+                        violations.iter().map(Violation::to_json).join(",")
+                    )
+                })
+                .join(",");
+            format!("[{entries}]")
+        }
+    }
+}
+
+enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+fn export_graph(
+    requirements: &Requirements,
+    seq: Option<&Sequence>,
+    format: GraphFormat,
+) -> String {
+    let pages: BTreeSet<usize> = match seq {
+        Some(seq) => seq.iter().copied().collect(),
+        None => requirements
+            .iter()
+            .flat_map(|(page, prereqs)| prereqs.iter().chain([page]))
+            .copied()
+            .collect(),
+    };
+
+    let edges = requirements
+        .iter()
+        .filter(|(page, _)| pages.contains(page))
+        .flat_map(|(page, prereqs)| {
+            prereqs
+                .iter()
+                .filter(|p| pages.contains(p))
+                .map(|prereq| (*prereq, *page))
+        })
+        .sorted()
+        .collect_vec();
+
+    let violated: HashSet<Rule> = match seq {
+        Some(seq) => find_violations(seq, requirements)
+            .iter()
+            .map(|v| v.rule)
+            .collect(),
+        None => HashSet::new(),
+    };
 
-                    j += 1;
+    let mut lines = vec![];
+    match format {
+        GraphFormat::Dot => {
+            lines.push("digraph rules {".to_string());
+            lines.extend(pages.iter().map(|page| format!("    {page};")));
+            for (prereq, page) in &edges {
+                if violated.contains(&(*prereq, *page)) {
+                    lines.push(format!("    {prereq} -> {page} [color=red, penwidth=2];"));
+                } else {
+                    lines.push(format!("    {prereq} -> {page};"));
                 }
             }
+            lines.push("}".to_string());
+        }
+        GraphFormat::Mermaid => {
+            lines.push("graph LR".to_string());
+            lines.extend(pages.iter().map(|page| format!("    p{page}[\"{page}\"]")));
+            for (i, (prereq, page)) in edges.iter().enumerate() {
+                lines.push(format!("    p{prereq} --> p{page}"));
+                if violated.contains(&(*prereq, *page)) {
+                    lines.push(format!("    linkStyle {i} stroke:red,stroke-width:2px"));
+                }
+            }
+        }
+    }
 
-            available.insert(page);
-            if !swapped {
-                i += 1;
+    lines.join("\n")
+}
+
+#[derive(Debug)]
+enum OrderError {
+    Cycle(Vec<usize>),
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderError::Cycle(pages) => {
+                let text = pages.iter().chain(pages.first()).join(" -> ");
+                write!(f, "Rules form a cycle: {text}")
             }
         }
     }
+}
+
+fn find_cycle(pages: &HashSet<usize>, requirements: &Requirements) -> Vec<usize> {
+    // Every remaining page has a remaining prerequisite, so walking
+    // backwards along prerequisites must eventually revisit a page.
+    let mut path = vec![*pages.iter().min().expect("Cycle must contain pages")];
 
-    res
+    loop {
+        let page = path[path.len() - 1];
+        let prereq = requirements[&page]
+            .iter()
+            .filter(|p| pages.contains(p))
+            .min()
+            .expect("Page in a cycle must have a prerequisite");
+
+        if let Some(start) = path.iter().position(|p| p == prereq) {
+            let mut cycle = path.split_off(start);
+            cycle.reverse();
+            return cycle;
+        }
+
+        path.push(*prereq);
+    }
 }
 
-fn solve1(seqs: &[Sequence], requirements: &Requirements) -> usize {
+fn order(seq: &Sequence, requirements: &Requirements) -> Result<Sequence, OrderError> {
+    let pages: HashSet<usize> = HashSet::from_iter(seq.iter().copied());
+
+    // Kahn's algorithm on the rules induced by the pages of the sequence
+    let mut num_prereqs: HashMap<usize, usize> = HashMap::new();
+    let mut dependants: HashMap<usize, Vec<usize>> = HashMap::new();
+    for page in seq {
+        let prereqs = requirements.get(page).into_iter().flatten();
+        for prereq in prereqs.filter(|p| pages.contains(p)) {
+            *num_prereqs.entry(*page).or_default() += 1;
+            dependants.entry(*prereq).or_default().push(*page);
+        }
+    }
+
+    // Pages without constraints between them keep their relative order
+    let position = index_positions(seq);
+    let mut available: BTreeSet<usize> = seq
+        .iter()
+        .filter(|page| !num_prereqs.contains_key(page))
+        .map(|page| position[page])
+        .collect();
+
+    let mut res = Sequence::with_capacity(seq.len());
+    while let Some(i) = available.pop_first() {
+        let page = seq[i];
+        res.push(page);
+
+        for dependant in dependants.get(&page).into_iter().flatten() {
+            let count = num_prereqs
+                .get_mut(dependant)
+                .expect("Dependant must have prerequisites");
+            *count -= 1;
+            if *count == 0 {
+                num_prereqs.remove(dependant);
+                available.insert(position[dependant]);
+            }
+        }
+    }
+
+    if !num_prereqs.is_empty() {
+        let remaining = HashSet::from_iter(num_prereqs.keys().copied());
+        return Err(OrderError::Cycle(find_cycle(&remaining, requirements)));
+    }
+
+    Ok(res)
+}
+
+#[derive(Debug, Default)]
+struct Delta {
+    now_valid: Vec<usize>,
+    now_invalid: Vec<usize>,
+}
+
+impl fmt::Display for Delta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "now valid: [{}], now invalid: [{}]",
+            self.now_valid.iter().join(","),
+            self.now_invalid.iter().join(",")
+        )
+    }
+}
+
+struct RuleStore {
+    requirements: Requirements,
+    seqs: Vec<Sequence>,
+    positions: Vec<Positions>,
+    containing: HashMap<usize, Vec<usize>>,
+    num_violations: Vec<usize>,
+}
+
+impl RuleStore {
+    fn new(rules: &[Rule], seqs: &[Sequence]) -> RuleStore {
+        let requirements = build_requirements(rules);
+
+        let positions = seqs.iter().map(index_positions).collect();
+
+        let mut containing: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, seq) in seqs.iter().enumerate() {
+            for page in seq.iter().unique() {
+                containing.entry(*page).or_default().push(i);
+            }
+        }
+
+        let num_violations = seqs
+            .iter()
+            .map(|seq| find_violations(seq, &requirements).len())
+            .collect();
+
+        RuleStore {
+            requirements,
+            seqs: seqs.to_vec(),
+            positions,
+            containing,
+            num_violations,
+        }
+    }
+
+    fn is_valid(&self, i: usize) -> bool {
+        self.num_violations[i] == 0
+    }
+
+    fn seqs_violating(&self, (prereq, page): Rule) -> Vec<usize> {
+        let (Some(a), Some(b)) = (self.containing.get(&prereq), self.containing.get(&page)) else {
+            return vec![];
+        };
+
+        // Only updates containing both pages are affected by the rule
+        let (shorter, longer) = if a.len() < b.len() { (a, b) } else { (b, a) };
+        shorter
+            .iter()
+            .filter(|i| longer.binary_search(i).is_ok())
+            .filter(|i| self.positions[**i][&prereq] > self.positions[**i][&page])
+            .copied()
+            .collect()
+    }
+
+    fn add_rule(&mut self, rule: Rule) -> Delta {
+        let (prereq, page) = rule;
+        if !self.requirements.entry(page).or_default().insert(prereq) {
+            return Delta::default();
+        }
+
+        let mut delta = Delta::default();
+        for i in self.seqs_violating(rule) {
+            if self.is_valid(i) {
+                delta.now_invalid.push(i);
+            }
+            self.num_violations[i] += 1;
+        }
+        delta
+    }
+
+    fn remove_rule(&mut self, rule: Rule) -> Delta {
+        let (prereq, page) = rule;
+        let Some(prereqs) = self.requirements.get_mut(&page) else {
+            return Delta::default();
+        };
+        if !prereqs.remove(&prereq) {
+            return Delta::default();
+        }
+        if prereqs.is_empty() {
+            self.requirements.remove(&page);
+        }
+
+        let mut delta = Delta::default();
+        for i in self.seqs_violating(rule) {
+            self.num_violations[i] -= 1;
+            if self.is_valid(i) {
+                delta.now_valid.push(i);
+            }
+        }
+        delta
+    }
+
+    fn valid_seqs(&self) -> impl Iterator<Item = &Sequence> {
+        self.seqs
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.is_valid(*i))
+            .map(|(_, seq)| seq)
+    }
+}
+
+fn solve1(seqs: &[Sequence], requirements: &Requirements, middle: MiddlePage) -> usize {
     seqs.iter()
         .filter(|seq| is_valid(seq, requirements))
-        .fold(0usize, |acc, seq| acc + seq[seq.len() / 2])
+        .fold(0usize, |acc, seq| acc + middle.of(seq))
 }
 
-fn solve2(seqs: &[Sequence], requirements: &Requirements) -> usize {
+fn solve2(
+    seqs: &[Sequence],
+    requirements: &Requirements,
+    middle: MiddlePage,
+) -> Result<usize, OrderError> {
     seqs.iter()
         .filter(|seq| !is_valid(seq, requirements))
         .map(|seq| order(seq, requirements))
-        .fold(0usize, |acc, seq| acc + seq[seq.len() / 2])
+        .try_fold(0usize, |acc, seq| Ok(acc + middle.of(&seq?)))
+}
+
+fn generate_input(num_pages: usize, num_rules: usize, num_seqs: usize, seed: u64) -> String {
+    let mut state = seed.max(1);
+    let mut random = |n: usize| {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % n as u64) as usize
+    };
+
+    // Rules always point forward in a hidden permutation so that they are acyclic
+    let mut rank = (0..num_pages).collect_vec();
+    for i in (1..num_pages).rev() {
+        rank.swap(i, random(i + 1));
+    }
+
+    let mut lines = vec![];
+    for _ in 0..num_rules {
+        let (a, b) = (random(num_pages), random(num_pages));
+        if a == b {
+            continue;
+        }
+        let (prereq, page) = if rank[a] < rank[b] { (a, b) } else { (b, a) };
+        lines.push(format!("{prereq}|{page}"));
+    }
+    lines.push(String::new());
+
+    for _ in 0..num_seqs {
+        let len = 2 * random(num_pages / 4) + 1;
+        let mut pages = (0..num_pages).collect_vec();
+        for i in 0..len {
+            pages.swap(i, i + random(num_pages - i));
+        }
+        pages.truncate(len);
+
+        // Roughly half of the updates start out valid
+        if random(2) == 0 {
+            pages.sort_by_key(|page| rank[*page]);
+        }
+        lines.push(pages.iter().join(","));
+    }
+
+    lines.join("\n")
+}
+
+fn bench() {
+    for (num_pages, num_rules) in [(100, 1_000), (1_000, 10_000), (5_000, 100_000)] {
+        let contents = generate_input(num_pages, num_rules, 200, num_rules as u64);
+        let (rules, seqs) =
+            parse_input(&contents, MiddlePage::Strict).expect("Generated input must be valid");
+        let requirements = build_requirements(&rules);
+
+        let start = Instant::now();
+        let expected = seqs
+            .iter()
+            .filter(|seq| is_valid_naive(seq, &requirements))
+            .count();
+        let naive = start.elapsed();
+
+        let start = Instant::now();
+        let num_valid = seqs
+            .iter()
+            .filter(|seq| is_valid(seq, &requirements))
+            .count();
+        let indexed = start.elapsed();
+
+        assert_eq!(expected, num_valid, "Indexed check disagrees with naive");
+        println!(
+            "{num_pages} pages, {} rules: {num_valid}/{} valid, naive {naive:?}, indexed {indexed:?}",
+            rules.len(),
+            seqs.len()
+        );
+    }
 }
 
 fn main() {
+    let mut middle = MiddlePage::Strict;
+    let mut args = vec![];
+    for arg in env::args().skip(1) {
+        match arg.strip_prefix("--middle=") {
+            Some("strict") => middle = MiddlePage::Strict,
+            Some("lower") => middle = MiddlePage::Lower,
+            Some("upper") => middle = MiddlePage::Upper,
+            Some(policy) => {
+                println!("Unknown middle page policy {policy:?}, expected strict, lower or upper");
+                return;
+            }
+            None => args.push(arg),
+        }
+    }
+
+    if args.first().map(String::as_str) == Some("bench") {
+        bench();
+        return;
+    }
+
     let filename = "input.txt";
     let contents = fs::read_to_string(filename).expect("Unable to read input file");
-    let (rules, seqs) = parse_input(&contents);
+    let (rules, seqs) = match parse_input(&contents, middle) {
+        Ok(input) => input,
+        Err(err) => {
+            println!("{err}");
+            return;
+        }
+    };
     let requirements = build_requirements(&rules);
 
-    let ans = solve1(&seqs, &requirements);
-    println!("Answer is {ans}");
+    match args.first().map(String::as_str) {
+        Some("explain") => {
+            let format = match args.get(1).map(String::as_str) {
+                Some("json") => ReportFormat::Json,
+                _ => ReportFormat::Text,
+            };
+            println!("{}", report(&seqs, &requirements, format));
+            return;
+        }
+        Some("graph") => {
+            let format = match args.get(1).map(String::as_str) {
+                Some("mermaid") => GraphFormat::Mermaid,
+                _ => GraphFormat::Dot,
+            };
+            let seq = args.get(2).map(|i| {
+                let i: usize = i.parse().expect("Update index must be a number");
+                seqs.get(i).expect("Update index out of range")
+            });
+            println!("{}", export_graph(&requirements, seq, format));
+            return;
+        }
+        Some("rules") => {
+            let mut store = RuleStore::new(&rules, &seqs);
+            for (i, line) in io::stdin().lines().enumerate() {
+                let line = line.expect("Unable to read from stdin");
+                let change = match line.trim().split_at_checked(1) {
+                    Some(("+", rule)) => parse_rule(rule, i + 1).map(|rule| store.add_rule(rule)),
+                    Some(("-", rule)) => {
+                        parse_rule(rule, i + 1).map(|rule| store.remove_rule(rule))
+                    }
+                    _ => {
+                        println!("Expected +X|Y or -X|Y");
+                        continue;
+                    }
+                };
+                match change {
+                    Ok(delta) => {
+                        let sum = store
+                            .valid_seqs()
+                            .fold(0usize, |acc, seq| acc + middle.of(seq));
+                        println!("{delta}, sum of valid middle pages: {sum}");
+                    }
+                    Err(err) => println!("{err}"),
+                }
+            }
+            return;
+        }
+        _ => (),
+    }
 
-    let ans = solve2(&seqs, &requirements);
+    let ans = solve1(&seqs, &requirements, middle);
     println!("Answer is {ans}");
+
+    match solve2(&seqs, &requirements, middle) {
+        Ok(ans) => println!("Answer is {ans}"),
+        Err(err) => println!("{err}"),
+    };
 }
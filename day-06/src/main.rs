@@ -1,8 +1,16 @@
-use std::collections::HashSet;
-use std::fs;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::style::{Print, PrintStyledContent, Stylize};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, Write};
 use std::ops::{Add, Sub};
+use std::time::{Duration, Instant};
+use std::{env, fs, thread};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
 enum Orientation {
     North,
     West,
@@ -36,10 +44,46 @@ impl Sub<(isize, isize)> for Position {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
+enum Turn {
+    Clockwise,
+    CounterClockwise,
+    Reverse,
+}
+
+// Turns taken at consecutive obstacles, repeating from the start
+#[derive(Debug, Clone)]
+struct TurnRule {
+    turns: Vec<Turn>,
+}
+
+impl TurnRule {
+    fn parse(text: &str) -> Option<TurnRule> {
+        let turns = text
+            .split(",")
+            .map(|turn| match turn {
+                "cw" => Some(Turn::Clockwise),
+                "ccw" => Some(Turn::CounterClockwise),
+                "reverse" => Some(Turn::Reverse),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        (!turns.is_empty()).then_some(TurnRule { turns })
+    }
+
+    fn apply(&self, state: &mut State) {
+        state.turn(&self.turns[state.turn_index]);
+        state.turn_index = (state.turn_index + 1) % self.turns.len();
+    }
+}
+
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
 struct State {
     orientation: Orientation,
     position: Position,
+    // The next turn in the TurnRule, so that loops repeat the turn sequence too
+    turn_index: usize,
 }
 
 impl State {
@@ -56,6 +100,26 @@ impl State {
         self.position = obstacle.clone() - self.get_step();
     }
 
+    fn turn(&mut self, turn: &Turn) {
+        match turn {
+            Turn::Clockwise => self.turn_cw(),
+            Turn::CounterClockwise => self.turn_ccw(),
+            Turn::Reverse => {
+                self.turn_cw();
+                self.turn_cw();
+            }
+        }
+    }
+
+    fn turn_ccw(&mut self) {
+        self.orientation = match self.orientation {
+            Orientation::North => Orientation::West,
+            Orientation::West => Orientation::South,
+            Orientation::South => Orientation::East,
+            Orientation::East => Orientation::North,
+        };
+    }
+
     fn turn_cw(&mut self) {
         self.orientation = match self.orientation {
             Orientation::North => Orientation::East,
@@ -66,47 +130,294 @@ impl State {
     }
 }
 
+#[derive(Debug, Clone)]
+enum Tile {
+    Portal(char, Position),
+    OneWay(Orientation),
+}
+
+impl Tile {
+    fn glyph(&self) -> char {
+        match self {
+            Tile::Portal(glyph, _) => *glyph,
+            Tile::OneWay(Orientation::North) => 'N',
+            Tile::OneWay(Orientation::East) => 'E',
+            Tile::OneWay(Orientation::South) => 'S',
+            Tile::OneWay(Orientation::West) => 'W',
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Map {
     obstacles: HashSet<Position>,
+    tiles: HashMap<Position, Tile>,
+    // Sorted columns of obstacles and tiles in every row and rows in every column
+    rows: Vec<Vec<isize>>,
+    cols: Vec<Vec<isize>>,
     width: isize,
     height: isize,
+    wrap: bool,
 }
 
 impl Map {
+    fn new(
+        obstacles: HashSet<Position>,
+        tiles: HashMap<Position, Tile>,
+        width: isize,
+        height: isize,
+    ) -> Map {
+        let mut rows = vec![vec![]; height as usize];
+        let mut cols = vec![vec![]; width as usize];
+        for stop in obstacles.iter().chain(tiles.keys()) {
+            rows[stop.row as usize].push(stop.col);
+            cols[stop.col as usize].push(stop.row);
+        }
+        rows.iter_mut().for_each(|row| row.sort());
+        cols.iter_mut().for_each(|col| col.sort());
+
+        Map {
+            obstacles,
+            tiles,
+            rows,
+            cols,
+            width,
+            height,
+            wrap: false,
+        }
+    }
+
     fn contains(&self, position: &Position) -> bool {
         (0..self.height).contains(&position.row) && (0..self.width).contains(&position.col)
     }
 
-    fn get_obstacle_ahead(&self, state: &State) -> Option<Position> {
-        let ahead = |p: &&Position| match state.orientation {
-            Orientation::North => p.col == state.position.col && p.row < state.position.row,
-            Orientation::West => p.row == state.position.row && p.col < state.position.col,
-            Orientation::South => p.col == state.position.col && p.row > state.position.row,
-            Orientation::East => p.row == state.position.row && p.col > state.position.col,
+    fn wrapped(&self, position: Position) -> Position {
+        if !self.wrap {
+            return position;
+        }
+
+        Position {
+            row: position.row.rem_euclid(self.height),
+            col: position.col.rem_euclid(self.width),
+        }
+    }
+
+    fn get_position_ahead(&self, state: &State) -> Position {
+        self.wrapped(state.position.clone() + state.get_step())
+    }
+
+    fn draw(&self) -> Vec<Vec<char>> {
+        let mut grid = vec![vec!['.'; self.width as usize]; self.height as usize];
+        for obstacle in &self.obstacles {
+            grid[obstacle.row as usize][obstacle.col as usize] = '#';
+        }
+        for (position, tile) in &self.tiles {
+            grid[position.row as usize][position.col as usize] = tile.glyph();
+        }
+        grid
+    }
+
+    fn enter(&self, state: &mut State) {
+        match self.tiles.get(&state.position) {
+            Some(Tile::Portal(_, exit)) => state.position = exit.clone(),
+            Some(Tile::OneWay(orientation)) => state.orientation = orientation.clone(),
+            None => (),
+        }
+    }
+
+    // Moves the guard onto the cell ahead and returns that cell, or returns
+    // None and leaves the guard in place if the cell is obstructed
+    fn advance(&self, state: &mut State, extra: Option<&Position>) -> Option<Position> {
+        let ahead = self.get_position_ahead(state);
+        if self.obstacles.contains(&ahead) || extra == Some(&ahead) {
+            return None;
+        }
+
+        state.position = ahead.clone();
+        self.enter(state);
+        Some(ahead)
+    }
+
+    fn get_distance_ahead(&self, state: &State, target: &Position) -> Option<isize> {
+        let Position { row, col } = state.position;
+
+        let (distance, length) = match state.orientation {
+            Orientation::North => (target.col == col).then_some((row - target.row, self.height)),
+            Orientation::West => (target.row == row).then_some((col - target.col, self.width)),
+            Orientation::South => (target.col == col).then_some((target.row - row, self.height)),
+            Orientation::East => (target.row == row).then_some((target.col - col, self.width)),
+        }?;
+
+        match distance {
+            distance if distance > 0 => Some(distance),
+            // Behind the guard is ahead on a torus, and its own cell a full lap away
+            distance if self.wrap => Some(length - (-distance).rem_euclid(length)),
+            _ => None,
+        }
+    }
+
+    fn get_stop_ahead(&self, state: &State, extra: Option<&Position>) -> Option<Position> {
+        let Position { row, col } = state.position;
+
+        // Without anything ahead the search continues from the opposite edge
+        let ahead = match state.orientation {
+            Orientation::North => {
+                let rows = &self.cols[col as usize];
+                let i = rows.partition_point(|r| *r < row);
+                let i = i
+                    .checked_sub(1)
+                    .or(rows.len().checked_sub(1).filter(|_| self.wrap));
+                i.map(|i| Position { row: rows[i], col })
+            }
+            Orientation::South => {
+                let rows = &self.cols[col as usize];
+                let i = rows.partition_point(|r| *r <= row);
+                let r = rows.get(i).or(rows.first().filter(|_| self.wrap));
+                r.map(|r| Position { row: *r, col })
+            }
+            Orientation::West => {
+                let cols = &self.rows[row as usize];
+                let i = cols.partition_point(|c| *c < col);
+                let i = i
+                    .checked_sub(1)
+                    .or(cols.len().checked_sub(1).filter(|_| self.wrap));
+                i.map(|i| Position { row, col: cols[i] })
+            }
+            Orientation::East => {
+                let cols = &self.rows[row as usize];
+                let i = cols.partition_point(|c| *c <= col);
+                let c = cols.get(i).or(cols.first().filter(|_| self.wrap));
+                c.map(|c| Position { row, col: *c })
+            }
         };
 
-        let obstacles_ahead = self.obstacles.iter().filter(ahead);
+        // The extra obstacle takes precedence if it is ahead and closer
+        let Some(extra_distance) = extra.and_then(|extra| self.get_distance_ahead(state, extra))
+        else {
+            return ahead;
+        };
 
-        match state.orientation {
-            Orientation::North => obstacles_ahead.max_by_key(|p| p.row),
-            Orientation::West => obstacles_ahead.max_by_key(|p| p.col),
-            Orientation::South => obstacles_ahead.min_by_key(|p| p.row),
-            Orientation::East => obstacles_ahead.min_by_key(|p| p.col),
+        match ahead {
+            Some(ahead)
+                if self.get_distance_ahead(state, &ahead).unwrap_or_default() <= extra_distance =>
+            {
+                Some(ahead)
+            }
+            _ => extra.cloned(),
         }
-        .cloned()
     }
 }
 
-fn parse_input(input: &str) -> (Map, State) {
-    let mut state = None;
-    let mut map = Map {
-        obstacles: HashSet::new(),
-        width: 0,
-        height: 0,
-    };
+#[derive(Debug)]
+enum ParseError {
+    Empty,
+    MissingGuard,
+    DuplicateGuard {
+        line: usize,
+        column: usize,
+        first_line: usize,
+        first_column: usize,
+    },
+    RaggedRow {
+        line: usize,
+        width: usize,
+        expected: usize,
+    },
+    UnknownTile {
+        line: usize,
+        column: usize,
+        tile: char,
+    },
+    UnpairedPortal {
+        line: usize,
+        column: usize,
+        portal: char,
+        count: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "Map is empty"),
+            ParseError::MissingGuard => write!(f, "Guard must have an initial state"),
+            ParseError::DuplicateGuard {
+                line,
+                column,
+                first_line,
+                first_column,
+            } => write!(
+                f,
+                "Line {line}, column {column}: second guard, the first is at line {first_line}, column {first_column}"
+            ),
+            ParseError::RaggedRow {
+                line,
+                width,
+                expected,
+            } => write!(
+                f,
+                "Line {line}: row has {width} cells but the first row has {expected}"
+            ),
+            ParseError::UnknownTile { line, column, tile } => {
+                write!(f, "Line {line}, column {column}: unknown tile {tile:?}")
+            }
+            ParseError::UnpairedPortal {
+                line,
+                column,
+                portal,
+                count,
+            } => write!(
+                f,
+                "Line {line}, column {column}: portal {portal} must appear twice but appears {count} times"
+            ),
+        }
+    }
+}
+
+fn get_location(position: &Position) -> (usize, usize) {
+    (position.row as usize + 1, position.col as usize + 1)
+}
+
+fn get_single_guard(guards: &[State]) -> Result<&State, ParseError> {
+    match guards {
+        [] => Err(ParseError::MissingGuard),
+        [guard] => Ok(guard),
+        [first, second, ..] => {
+            let (first_line, first_column) = get_location(&first.position);
+            let (line, column) = get_location(&second.position);
+            Err(ParseError::DuplicateGuard {
+                line,
+                column,
+                first_line,
+                first_column,
+            })
+        }
+    }
+}
+
+fn parse_input(input: &str) -> Result<(Map, Vec<State>), ParseError> {
+    let mut guards = vec![];
+    let mut obstacles = HashSet::new();
+    let mut tiles = HashMap::new();
+    let mut portals: HashMap<char, Vec<Position>> = HashMap::new();
+
+    let lines = input.trim_end().lines().collect::<Vec<_>>();
+    let height = lines.len();
+    let width = lines.first().map_or(0, |line| line.chars().count());
+    if width == 0 {
+        return Err(ParseError::Empty);
+    }
+
+    for (i, line) in lines.iter().enumerate() {
+        let row_width = line.chars().count();
+        if row_width != width {
+            return Err(ParseError::RaggedRow {
+                line: i + 1,
+                width: row_width,
+                expected: width,
+            });
+        }
 
-    for (i, line) in input.trim().split("\n").enumerate() {
         for (j, cell) in line.chars().enumerate() {
             let position = Position {
                 row: i as isize,
@@ -115,119 +426,819 @@ fn parse_input(input: &str) -> (Map, State) {
 
             match cell {
                 '#' => {
-                    map.obstacles.insert(position);
+                    obstacles.insert(position);
                 }
-                '^' => {
-                    state = Some(State {
-                        orientation: Orientation::North,
-                        position,
-                    })
+                '^' => guards.push(State {
+                    orientation: Orientation::North,
+                    position,
+                    turn_index: 0,
+                }),
+                '>' => guards.push(State {
+                    orientation: Orientation::East,
+                    position,
+                    turn_index: 0,
+                }),
+                'v' => guards.push(State {
+                    orientation: Orientation::South,
+                    position,
+                    turn_index: 0,
+                }),
+                '<' => guards.push(State {
+                    orientation: Orientation::West,
+                    position,
+                    turn_index: 0,
+                }),
+                'N' => {
+                    tiles.insert(position, Tile::OneWay(Orientation::North));
                 }
-                '>' => {
-                    state = Some(State {
-                        orientation: Orientation::East,
-                        position,
-                    })
+                'E' => {
+                    tiles.insert(position, Tile::OneWay(Orientation::East));
                 }
-                'v' => {
-                    state = Some(State {
-                        orientation: Orientation::South,
-                        position,
-                    })
+                'S' => {
+                    tiles.insert(position, Tile::OneWay(Orientation::South));
+                }
+                'W' => {
+                    tiles.insert(position, Tile::OneWay(Orientation::West));
                 }
-                '<' => {
-                    state = Some(State {
-                        orientation: Orientation::West,
-                        position,
+                '0'..='9' => portals.entry(cell).or_default().push(position),
+                '.' => (),
+                tile => {
+                    return Err(ParseError::UnknownTile {
+                        line: i + 1,
+                        column: j + 1,
+                        tile,
                     })
                 }
-                _ => (),
             };
+        }
+    }
 
-            if map.height == 0 {
-                map.width += 1;
-            }
+    if guards.is_empty() {
+        return Err(ParseError::MissingGuard);
+    }
+
+    let mut portals = portals.into_iter().collect::<Vec<_>>();
+    portals.sort_by_key(|(glyph, _)| *glyph);
+    for (glyph, ends) in portals {
+        let [a, b] = ends.as_slice() else {
+            let (line, column) = get_location(&ends[0]);
+            return Err(ParseError::UnpairedPortal {
+                line,
+                column,
+                portal: glyph,
+                count: ends.len(),
+            });
+        };
+        tiles.insert(a.clone(), Tile::Portal(glyph, b.clone()));
+        tiles.insert(b.clone(), Tile::Portal(glyph, a.clone()));
+    }
+
+    let map = Map::new(obstacles, tiles, width as isize, height as isize);
+    Ok((map, guards))
+}
+
+#[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
+enum StopKind {
+    Turn,
+    Portal,
+    OneWay,
+    Wrap,
+}
+
+impl StopKind {
+    fn name(&self) -> &str {
+        match self {
+            StopKind::Turn => "turn",
+            StopKind::Portal => "portal",
+            StopKind::OneWay => "one-way",
+            StopKind::Wrap => "wrap",
         }
-        map.height += 1;
     }
+}
 
-    (map, state.expect("Guard must have an initial state"))
+// The guard state right after facing an obstacle, leaving a tile, or, on a torus
+// with nothing ahead, setting off to circle forever
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+struct Stop {
+    state: State,
+    kind: StopKind,
 }
 
-fn can_loop(map: &Map, initial_state: &State) -> bool {
+fn find_loop(
+    map: &Map,
+    rule: &TurnRule,
+    initial_state: &State,
+    extra: Option<&Position>,
+) -> Option<Vec<Stop>> {
     let mut state = initial_state.clone();
-    let mut corners: Vec<State> = vec![];
+    let mut events: Vec<Stop> = vec![];
+    let mut seen = HashMap::<Stop, usize>::new();
+
+    loop {
+        // With nothing ahead the guard either leaves or circles the torus forever
+        let Some(stop) = map.get_stop_ahead(&state, extra) else {
+            let kind = StopKind::Wrap;
+            return map.wrap.then(|| vec![Stop { state, kind }]);
+        };
 
-    while let Some(obstacle) = map.get_obstacle_ahead(&state) {
-        state.move_to(&obstacle);
+        let kind = match map.tiles.get(&stop) {
+            _ if extra == Some(&stop) || map.obstacles.contains(&stop) => StopKind::Turn,
+            Some(Tile::Portal(..)) => StopKind::Portal,
+            Some(Tile::OneWay(_)) => StopKind::OneWay,
+            None => unreachable!("Stops are obstacles or tiles"),
+        };
+        if kind == StopKind::Turn {
+            state.move_to(&stop);
+            state.position = map.wrapped(state.position);
+        } else {
+            state.position = stop;
+            map.enter(&mut state);
+        }
 
-        if corners.contains(&state) {
-            return true;
+        // Arriving on a tile and then facing an obstacle from the same cell are
+        // distinct events even though the guard state is the same
+        let event = Stop {
+            state: state.clone(),
+            kind,
+        };
+        if let Some(start) = seen.get(&event) {
+            return Some(events.split_off(*start));
         }
 
-        corners.push(state.clone());
+        seen.insert(event.clone(), events.len());
+        events.push(event);
 
-        state.turn_cw();
+        if kind == StopKind::Turn {
+            rule.apply(&mut state);
+        }
     }
+}
 
-    false
+fn can_loop(map: &Map, rule: &TurnRule, initial_state: &State, extra: Option<&Position>) -> bool {
+    find_loop(map, rule, initial_state, extra).is_some()
 }
 
-fn solve1(map: &Map, initial_state: &State) -> usize {
+fn solve1(map: &Map, rule: &TurnRule, initial_state: &State) -> usize {
     let mut state = initial_state.clone();
     let mut traversed = HashSet::<Position>::new();
+    let mut seen = HashSet::<State>::new();
 
-    while map.contains(&state.position) {
+    while map.contains(&state.position) && seen.insert(state.clone()) {
         traversed.insert(state.position.clone());
 
-        let next_position = state.position.clone() + state.get_step();
-
-        if !map.obstacles.contains(&next_position) {
-            state.position = next_position;
-        } else {
-            state.turn_cw();
+        match map.advance(&mut state, None) {
+            // A portal is entered even though the guard ends up elsewhere
+            Some(entered) if map.contains(&entered) => {
+                traversed.insert(entered);
+            }
+            Some(_) => (),
+            None => rule.apply(&mut state),
         }
     }
 
     traversed.len()
 }
 
-fn solve2(map: &Map, initial_state: &State) -> usize {
+fn get_loop_candidates(
+    map: &Map,
+    rule: &TurnRule,
+    initial_state: &State,
+) -> Vec<(State, Position)> {
     let mut state = initial_state.clone();
-    let mut num_loops = 0;
+    let mut candidates = vec![];
     let mut traversed = HashSet::<Position>::new();
+    let mut seen = HashSet::<State>::new();
 
-    while map.contains(&state.position) {
+    while map.contains(&state.position) && seen.insert(state.clone()) {
         traversed.insert(state.position.clone());
 
-        let next_position = state.position.clone() + state.get_step();
+        let next_position = map.get_position_ahead(&state);
+
+        // The guard state when the obstruction is first hit is all a candidate needs
+        if !traversed.contains(&next_position)
+            && map.contains(&next_position)
+            && !map.obstacles.contains(&next_position)
+            && !map.tiles.contains_key(&next_position)
+        {
+            candidates.push((state.clone(), next_position));
+        }
+
+        if map.advance(&mut state, None).is_none() {
+            rule.apply(&mut state);
+        }
+    }
+
+    candidates
+}
+
+fn search_candidates<T, F>(candidates: &[(State, Position)], num_threads: usize, check: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&State, &Position) -> Option<T> + Sync,
+{
+    let chunk_size = candidates.len().div_ceil(num_threads.max(1)).max(1);
+
+    thread::scope(|scope| {
+        let workers = candidates
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let check = &check;
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .filter_map(|(state, obstruction)| check(state, obstruction))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
 
-        if !traversed.contains(&next_position) && map.contains(&next_position) {
-            let mut map = map.clone();
-            map.obstacles.insert(state.position.clone() + state.get_step());
-            if can_loop(&map, &state) {
-                num_loops += 1;
+        // Joining in spawn order keeps the results in path order
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Worker thread panicked"))
+            .collect()
+    })
+}
+
+fn solve2(map: &Map, rule: &TurnRule, initial_state: &State, num_threads: usize) -> usize {
+    let candidates = get_loop_candidates(map, rule, initial_state);
+    let check = |state: &State, obstruction: &Position| {
+        can_loop(map, rule, state, Some(obstruction)).then_some(())
+    };
+    search_candidates(&candidates, num_threads, check).len()
+}
+
+#[derive(Debug)]
+struct Trap {
+    obstruction: Position,
+    cycle: Vec<Stop>,
+}
+
+impl Trap {
+    fn to_json(&self) -> String {
+        let cycle = self
+            .cycle
+            .iter()
+            .map(|Stop { state, kind }| {
+                format!(
+                    "{{\"event\":\"{}\",\"row\":{},\"col\":{},\"orientation\":\"{:?}\"}}",
+                    kind.name(),
+                    state.position.row,
+                    state.position.col,
+                    state.orientation
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            "{{\"obstruction\":{{\"row\":{},\"col\":{}}},\"cycle\":[{cycle}]}}",
+            self.obstruction.row, self.obstruction.col
+        )
+    }
+
+    fn render(&self, map: &Map, rule: &TurnRule) -> String {
+        let mut grid = map.draw();
+
+        // Retrace the cycle cell by cell, marking crossings and turns with +
+        let start = &self.cycle[0].state;
+        let mut state = start.clone();
+        loop {
+            let glyph = match state.orientation {
+                Orientation::North | Orientation::South => '|',
+                Orientation::East | Orientation::West => '-',
+            };
+            let cell = &mut grid[state.position.row as usize][state.position.col as usize];
+            *cell = match *cell {
+                '.' => glyph,
+                '|' | '-' if *cell != glyph => '+',
+                other => other,
+            };
+
+            if map.advance(&mut state, Some(&self.obstruction)).is_none() {
+                rule.apply(&mut state);
+            }
+            if state == *start {
+                break;
             }
         }
 
-        if !map.obstacles.contains(&next_position) {
-            state.position = next_position;
+        grid[self.obstruction.row as usize][self.obstruction.col as usize] = 'O';
+
+        grid.iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn find_traps(map: &Map, rule: &TurnRule, initial_state: &State, num_threads: usize) -> Vec<Trap> {
+    let candidates = get_loop_candidates(map, rule, initial_state);
+    let check = |state: &State, obstruction: &Position| {
+        find_loop(map, rule, state, Some(obstruction)).map(|cycle| Trap {
+            obstruction: obstruction.clone(),
+            cycle,
+        })
+    };
+    search_candidates(&candidates, num_threads, check)
+}
+
+#[derive(Debug)]
+enum GuardStatus {
+    Patrolling,
+    Exited(usize),
+    Looping(usize),
+}
+
+#[derive(Debug)]
+struct Guard {
+    state: State,
+    visited: HashSet<Position>,
+    seen: HashSet<State>,
+    status: GuardStatus,
+}
+
+impl Guard {
+    fn new(initial_state: &State) -> Guard {
+        Guard {
+            state: initial_state.clone(),
+            visited: HashSet::new(),
+            seen: HashSet::new(),
+            status: GuardStatus::Patrolling,
+        }
+    }
+
+    fn step(&mut self, map: &Map, rule: &TurnRule, steps: usize) {
+        if !map.contains(&self.state.position) {
+            self.status = GuardStatus::Exited(steps);
+            return;
+        }
+
+        if !self.seen.insert(self.state.clone()) {
+            self.status = GuardStatus::Looping(steps);
+            return;
+        }
+
+        self.visited.insert(self.state.position.clone());
+
+        match map.advance(&mut self.state, None) {
+            Some(entered) if map.contains(&entered) => {
+                self.visited.insert(entered);
+            }
+            Some(_) => (),
+            None => rule.apply(&mut self.state),
+        }
+    }
+}
+
+fn simulate_guards(map: &Map, initial_states: &[State], rule: &TurnRule) -> Vec<Guard> {
+    let mut guards = initial_states.iter().map(Guard::new).collect::<Vec<_>>();
+
+    // Guards move in lockstep and do not block each other
+    let mut steps = 0;
+    while guards
+        .iter()
+        .any(|guard| matches!(guard.status, GuardStatus::Patrolling))
+    {
+        for guard in &mut guards {
+            if let GuardStatus::Patrolling = guard.status {
+                guard.step(map, rule, steps);
+            }
+        }
+        steps += 1;
+    }
+
+    guards
+}
+
+struct Patrol {
+    state: State,
+    extra: Option<Position>,
+    visited: HashSet<Position>,
+    history: Vec<State>,
+    seen: HashMap<State, usize>,
+    cycle: HashSet<Position>,
+}
+
+impl Patrol {
+    fn new(initial_state: &State, extra: Option<Position>) -> Patrol {
+        Patrol {
+            state: initial_state.clone(),
+            extra,
+            visited: HashSet::new(),
+            history: vec![],
+            seen: HashMap::new(),
+            cycle: HashSet::new(),
+        }
+    }
+
+    fn is_done(&self, map: &Map) -> bool {
+        !self.cycle.is_empty() || !map.contains(&self.state.position)
+    }
+
+    fn step(&mut self, map: &Map, rule: &TurnRule) {
+        if let Some(start) = self.seen.get(&self.state) {
+            self.cycle = self.history[*start..]
+                .iter()
+                .map(|state| state.position.clone())
+                .collect();
+            return;
+        }
+
+        self.visited.insert(self.state.position.clone());
+        self.seen.insert(self.state.clone(), self.history.len());
+        self.history.push(self.state.clone());
+
+        match map.advance(&mut self.state, self.extra.as_ref()) {
+            Some(entered) if map.contains(&entered) => {
+                self.visited.insert(entered);
+            }
+            Some(_) => (),
+            None => rule.apply(&mut self.state),
+        }
+    }
+}
+
+struct Controls {
+    delay: Duration,
+    paused: bool,
+}
+
+enum Command {
+    Continue,
+    Skip,
+    Quit,
+}
+
+impl Controls {
+    fn wait(&mut self, delay: Duration) -> io::Result<Command> {
+        let deadline = Instant::now() + delay;
+        loop {
+            let timeout = if self.paused {
+                Duration::from_secs(3600)
+            } else {
+                deadline.saturating_duration_since(Instant::now())
+            };
+
+            if !event::poll(timeout)? {
+                if self.paused {
+                    continue;
+                }
+                return Ok(Command::Continue);
+            }
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(Command::Quit),
+                KeyCode::Char('s') => return Ok(Command::Skip),
+                KeyCode::Char(' ') => self.paused = !self.paused,
+                KeyCode::Char('n') | KeyCode::Right if self.paused => return Ok(Command::Continue),
+                KeyCode::Char('+') | KeyCode::Up => self.delay /= 2,
+                KeyCode::Char('-') | KeyCode::Down => {
+                    self.delay = (self.delay * 2).max(Duration::from_millis(1))
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+fn render(out: &mut impl Write, map: &Map, patrol: &Patrol, status: &str) -> io::Result<()> {
+    let (cols, rows) = terminal::size()?;
+    let view_width = (cols as isize).min(map.width);
+    let view_height = (rows as isize - 2).clamp(1, map.height.max(1));
+
+    // Keep the guard in the middle of the view when the map does not fit
+    let position = &patrol.state.position;
+    let top = (position.row - view_height / 2).clamp(0, map.height - view_height);
+    let left = (position.col - view_width / 2).clamp(0, map.width - view_width);
+
+    queue!(out, MoveTo(0, 0), Clear(ClearType::All))?;
+    for row in top..top + view_height {
+        queue!(out, MoveTo(0, (row - top) as u16))?;
+        for col in left..left + view_width {
+            let cell = Position { row, col };
+            let content = if cell == *position {
+                let glyph = match patrol.state.orientation {
+                    Orientation::North => '^',
+                    Orientation::East => '>',
+                    Orientation::South => 'v',
+                    Orientation::West => '<',
+                };
+                glyph.bold().green()
+            } else if patrol.extra.as_ref() == Some(&cell) {
+                'O'.bold().yellow()
+            } else if map.obstacles.contains(&cell) {
+                '#'.white()
+            } else if let Some(tile) = map.tiles.get(&cell) {
+                tile.glyph().magenta()
+            } else if patrol.cycle.contains(&cell) {
+                '+'.red()
+            } else if patrol.visited.contains(&cell) {
+                'X'.dark_cyan()
+            } else {
+                '.'.dark_grey()
+            };
+            queue!(out, PrintStyledContent(content))?;
+        }
+    }
+
+    queue!(out, MoveTo(0, view_height as u16 + 1), Print(status))?;
+    out.flush()
+}
+
+fn play(map: &Map, rule: &TurnRule, initial_state: &State, show_loops: bool) -> io::Result<()> {
+    let patrols = if show_loops {
+        get_loop_candidates(map, rule, initial_state)
+            .into_iter()
+            .map(|(state, obstruction)| Patrol::new(&state, Some(obstruction)))
+            .collect()
+    } else {
+        vec![Patrol::new(initial_state, None)]
+    };
+
+    let mut controls = Controls {
+        delay: Duration::from_millis(50),
+        paused: false,
+    };
+    let help = "space: pause  n: step  +/-: speed  s: skip  q: quit";
+
+    let mut out = io::stdout();
+    let num_patrols = patrols.len();
+    let mut num_loops = 0;
+    for (i, mut patrol) in patrols.into_iter().enumerate() {
+        loop {
+            let status = if show_loops {
+                format!(
+                    "Obstruction {}/{num_patrols} | loops {num_loops} | {:?} | {help}",
+                    i + 1,
+                    controls.delay
+                )
+            } else {
+                let visited = patrol.visited.len();
+                format!("Visited {visited} | {:?} | {help}", controls.delay)
+            };
+            render(&mut out, map, &patrol, &status)?;
+
+            if patrol.is_done(map) {
+                break;
+            }
+
+            match controls.wait(controls.delay)? {
+                Command::Continue => patrol.step(map, rule),
+                Command::Skip => break,
+                Command::Quit => return Ok(()),
+            }
+        }
+
+        // Linger on a detected loop so that it can be seen
+        let linger = if patrol.cycle.is_empty() {
+            controls.delay
         } else {
-            state.turn_cw();
+            num_loops += 1;
+            controls.delay * 20
+        };
+        if let Command::Quit = controls.wait(linger)? {
+            return Ok(());
+        }
+    }
+
+    controls.paused = true;
+    controls.wait(Duration::ZERO).map(|_| ())
+}
+
+fn animate(map: &Map, rule: &TurnRule, initial_state: &State, show_loops: bool) -> io::Result<()> {
+    terminal::enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+
+    let result = play(map, rule, initial_state, show_loops);
+
+    execute!(io::stdout(), Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        ((self.next() >> 11) as f64) < probability * (1u64 << 53) as f64
+    }
+}
+
+#[derive(Debug)]
+enum Requirement {
+    Any,
+    Exits,
+    MinLoops(usize),
+}
+
+fn generate_map(
+    width: usize,
+    height: usize,
+    density: f64,
+    seed: u64,
+    requirement: &Requirement,
+    rule: &TurnRule,
+    num_threads: usize,
+) -> Option<String> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    let mut rng = XorShift(seed.max(1));
+
+    for _ in 0..1000 {
+        let mut grid = (0..height)
+            .map(|_| {
+                (0..width)
+                    .map(|_| if rng.chance(density) { '#' } else { '.' })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        grid[rng.below(height)][rng.below(width)] = '^';
+
+        let text = grid
+            .iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        // Check the map the way it will be read back
+        let (map, guards) = parse_input(&text).expect("Generated map must be valid");
+        let guard = &guards[0];
+        let satisfied = match requirement {
+            Requirement::Any => true,
+            Requirement::Exits => !can_loop(&map, rule, guard, None),
+            Requirement::MinLoops(n) => {
+                !can_loop(&map, rule, guard, None) && solve2(&map, rule, guard, num_threads) >= *n
+            }
+        };
+
+        if satisfied {
+            return Some(text + "\n");
         }
     }
 
-    num_loops
+    None
 }
 
 fn main() {
+    let mut num_threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut turn_rule = TurnRule {
+        turns: vec![Turn::Clockwise],
+    };
+    let mut seed = 1;
+    let mut wrap = false;
+    let mut requirement = Requirement::Any;
+    let mut args = vec![];
+    for arg in env::args().skip(1) {
+        if let Some(value) = arg.strip_prefix("--seed=") {
+            seed = value.parse().expect("Seed must be a number");
+        } else if arg == "--wrap" {
+            wrap = true;
+        } else if arg == "--exits" {
+            requirement = Requirement::Exits;
+        } else if let Some(n) = arg.strip_prefix("--min-loops=") {
+            requirement = Requirement::MinLoops(n.parse().expect("Loop count must be a number"));
+        } else if let Some(threads) = arg.strip_prefix("--threads=") {
+            num_threads = threads.parse().expect("Thread count must be a number");
+        } else if let Some(turns) = arg.strip_prefix("--turns=") {
+            match TurnRule::parse(turns) {
+                Some(rule) => turn_rule = rule,
+                None => {
+                    println!("Turns must be a comma separated list of cw, ccw and reverse");
+                    return;
+                }
+            }
+        } else {
+            args.push(arg);
+        }
+    }
+
+    if args.first().map(String::as_str) == Some("generate") {
+        let [width, height, density] = [1, 2, 3].map(|i| args.get(i));
+        let width = width.map_or(130, |w| w.parse().expect("Width must be a number"));
+        let height = height.map_or(130, |h| h.parse().expect("Height must be a number"));
+        let density = density.map_or(0.01, |d| d.parse().expect("Density must be a number"));
+
+        match generate_map(
+            width,
+            height,
+            density,
+            seed,
+            &requirement,
+            &turn_rule,
+            num_threads,
+        ) {
+            Some(text) => print!("{text}"),
+            None => println!("Unable to generate a map satisfying {requirement:?}"),
+        }
+        return;
+    }
+
     let filename = "input.txt";
     let contents = fs::read_to_string(filename).expect("Unable to read input file");
-    let (map, guard) = parse_input(&contents);
+    let (mut map, guards) = match parse_input(&contents) {
+        Ok(input) => input,
+        Err(err) => {
+            println!("{err}");
+            return;
+        }
+    };
+    map.wrap = wrap;
+
+    if args.first().map(String::as_str) == Some("patrol") {
+        let patrols = simulate_guards(&map, &guards, &turn_rule);
+        for (i, patrol) in patrols.iter().enumerate() {
+            let Position { row, col } = guards[i].position;
+            let orientation = &guards[i].orientation;
+            let outcome = match patrol.status {
+                GuardStatus::Exited(steps) => format!("exited after {steps} steps"),
+                GuardStatus::Looping(steps) => format!("looped after {steps} steps"),
+                GuardStatus::Patrolling => {
+                    unreachable!("Simulation ends with every guard done")
+                }
+            };
+            println!(
+                "Guard {i} at ({row}, {col}) facing {orientation:?}: visited {} cells, {outcome}",
+                patrol.visited.len()
+            );
+        }
 
-    let answer = solve1(&map, &guard);
+        let visited = patrols
+            .iter()
+            .flat_map(|patrol| patrol.visited.iter())
+            .collect::<HashSet<_>>();
+        println!("Cells visited by any guard: {}", visited.len());
+        return;
+    }
+
+    let guard = match get_single_guard(&guards) {
+        Ok(guard) => guard,
+        Err(err) => {
+            println!("{err}");
+            return;
+        }
+    };
+
+    // Then every candidate counts as a loop, which is not a meaningful answer
+    let already_loops = can_loop(&map, &turn_rule, guard, None);
+
+    match args.first().map(String::as_str) {
+        Some("traps") if already_loops => {
+            println!("The guard already loops without an obstruction");
+            return;
+        }
+        Some("traps") => {
+            let traps = find_traps(&map, &turn_rule, guard, num_threads);
+            match args.get(1) {
+                Some(i) => {
+                    let i: usize = i.parse().expect("Trap index must be a number");
+                    let trap = traps.get(i).expect("Trap index out of range");
+                    println!("{}", trap.render(&map, &turn_rule));
+                }
+                None => {
+                    let traps = traps.iter().map(Trap::to_json).collect::<Vec<_>>();
+                    println!("[{}]", traps.join(","));
+                }
+            }
+            return;
+        }
+        Some("play") => {
+            let show_loops = args.get(1).map(String::as_str) == Some("loops");
+            if let Err(err) = animate(&map, &turn_rule, guard, show_loops) {
+                println!("Animation failed: {err}");
+            }
+            return;
+        }
+        _ => (),
+    }
+
+    let answer = solve1(&map, &turn_rule, guard);
     println!("Answer is {answer}");
 
-    let answer = solve2(&map, &guard);
+    if already_loops {
+        println!("The guard already loops without an obstruction");
+        return;
+    }
+
+    let answer = solve2(&map, &turn_rule, guard, num_threads);
     println!("Answer is {answer}");
 }
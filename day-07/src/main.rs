@@ -1,63 +1,425 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
-use std::fs;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use std::{env, fs};
 
-#[derive(Debug, Clone)]
-enum Operation {
-    Add,
-    Multiply,
-    Concatenate,
+// Targets beyond u64 need the `wide` feature
+#[cfg(not(feature = "wide"))]
+type Value = u64;
+#[cfg(feature = "wide")]
+type Value = u128;
+
+// Number of decimal digits in `b`, as a power of ten to shift by
+fn digit_shift(b: Value) -> Option<Value> {
+    Value::checked_pow(10, b.checked_ilog10().unwrap_or(0) + 1)
 }
 
-impl Operation {
-    fn apply(&self, a: u64, b: u64) -> u64 {
-        match self {
-            Operation::Add => a + b,
-            Operation::Multiply => a * b,
-            Operation::Concatenate => format!("{}{}", a, b).parse().unwrap(),
-        }
+trait Operator: fmt::Debug + Send + Sync {
+    // None when the result is undefined, e.g. below zero, or does not fit in a Value.
+    // Overflow is therefore treated like any other value past the target.
+    fn apply(&self, a: Value, b: Value) -> Option<Value>;
+
+    fn symbol(&self) -> &str;
+
+    // Whether `apply(a, b) >= a` for every positive `b`
+    fn is_monotone(&self) -> bool;
+
+    // Finds `a` such that `apply(a, b) == result`, if the operator supports it
+    fn unapply(&self, _result: Value, _b: Value) -> Option<Value> {
+        None
+    }
+
+    fn is_invertible(&self) -> bool {
+        false
+    }
+
+    // Whether `apply(a, b) == result` for every `a`, which `unapply` cannot express
+    fn absorbs(&self, _result: Value, _b: Value) -> bool {
+        false
+    }
+
+    // Binding strength under conventional rules, None when there is no convention
+    fn precedence(&self) -> Option<u8> {
+        Some(1)
+    }
+
+    fn is_right_associative(&self) -> bool {
+        false
     }
 }
 
-impl fmt::Display for Operation {
+type Operation = Arc<dyn Operator>;
+
+impl fmt::Display for dyn Operator {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
+
+#[derive(Debug)]
+struct Add;
+
+impl Operator for Add {
+    fn apply(&self, a: Value, b: Value) -> Option<Value> {
+        a.checked_add(b)
+    }
+
+    fn symbol(&self) -> &str {
+        "+"
+    }
+
+    fn is_monotone(&self) -> bool {
+        true
+    }
+
+    fn unapply(&self, result: Value, b: Value) -> Option<Value> {
+        result.checked_sub(b)
+    }
+
+    fn is_invertible(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+struct Multiply;
+
+impl Operator for Multiply {
+    fn apply(&self, a: Value, b: Value) -> Option<Value> {
+        a.checked_mul(b)
+    }
+
+    fn symbol(&self) -> &str {
+        "*"
+    }
+
+    fn is_monotone(&self) -> bool {
+        true
+    }
+
+    fn unapply(&self, result: Value, b: Value) -> Option<Value> {
+        (b != 0 && result.is_multiple_of(b)).then(|| result / b)
+    }
+
+    fn is_invertible(&self) -> bool {
+        true
+    }
+
+    fn absorbs(&self, result: Value, b: Value) -> bool {
+        b == 0 && result == 0
+    }
+
+    fn precedence(&self) -> Option<u8> {
+        Some(2)
+    }
+}
+
+#[derive(Debug)]
+struct Concatenate;
+
+impl Operator for Concatenate {
+    fn apply(&self, a: Value, b: Value) -> Option<Value> {
+        a.checked_mul(digit_shift(b)?)?.checked_add(b)
+    }
+
+    fn symbol(&self) -> &str {
+        "|"
+    }
+
+    fn is_monotone(&self) -> bool {
+        true
+    }
+
+    fn unapply(&self, result: Value, b: Value) -> Option<Value> {
+        let shift = digit_shift(b)?;
+        (result % shift == b).then(|| result / shift)
+    }
+
+    fn is_invertible(&self) -> bool {
+        true
+    }
+
+    fn precedence(&self) -> Option<u8> {
+        None
+    }
+}
+
+#[derive(Debug)]
+struct Subtract;
+
+impl Operator for Subtract {
+    fn apply(&self, a: Value, b: Value) -> Option<Value> {
+        a.checked_sub(b)
+    }
+
+    fn symbol(&self) -> &str {
+        "-"
+    }
+
+    fn is_monotone(&self) -> bool {
+        false
+    }
+
+    fn unapply(&self, result: Value, b: Value) -> Option<Value> {
+        result.checked_add(b)
+    }
+
+    fn is_invertible(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+struct Divide;
+
+impl Operator for Divide {
+    fn apply(&self, a: Value, b: Value) -> Option<Value> {
+        a.checked_div(b)
+    }
+
+    fn symbol(&self) -> &str {
+        "/"
+    }
+
+    fn is_monotone(&self) -> bool {
+        false
+    }
+
+    fn precedence(&self) -> Option<u8> {
+        Some(2)
+    }
+}
+
+#[derive(Debug)]
+struct Power;
+
+impl Operator for Power {
+    fn apply(&self, a: Value, b: Value) -> Option<Value> {
+        a.checked_pow(u32::try_from(b).ok()?)
+    }
+
+    fn symbol(&self) -> &str {
+        "**"
+    }
+
+    fn is_monotone(&self) -> bool {
+        true
+    }
+
+    fn precedence(&self) -> Option<u8> {
+        Some(3)
+    }
+
+    fn is_right_associative(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Debug)]
+struct Xor;
+
+impl Operator for Xor {
+    fn apply(&self, a: Value, b: Value) -> Option<Value> {
+        Some(a ^ b)
+    }
+
+    fn symbol(&self) -> &str {
+        "^"
+    }
+
+    fn is_monotone(&self) -> bool {
+        false
+    }
+
+    fn unapply(&self, result: Value, b: Value) -> Option<Value> {
+        Some(result ^ b)
+    }
+
+    fn is_invertible(&self) -> bool {
+        true
+    }
+}
+
+fn get_operator(symbol: &str) -> Option<Operation> {
+    let operators: [Operation; 7] = [
+        Arc::new(Add),
+        Arc::new(Multiply),
+        Arc::new(Concatenate),
+        Arc::new(Subtract),
+        Arc::new(Divide),
+        Arc::new(Power),
+        Arc::new(Xor),
+    ];
+    operators.into_iter().find(|op| op.symbol() == symbol)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Evaluation {
+    LeftToRight,
+    // Concatenation has no conventional precedence, so its level is chosen here
+    Precedence { concatenation: u8 },
+}
+
+impl Evaluation {
+    fn level(&self, op: &dyn Operator) -> u8 {
         match self {
-            Operation::Add => write!(f, "+"),
-            Operation::Multiply => write!(f, "*"),
-            Operation::Concatenate => write!(f, "|"),
+            Evaluation::LeftToRight => 0,
+            Evaluation::Precedence { concatenation } => op.precedence().unwrap_or(*concatenation),
+        }
+    }
+
+    // Whether the pending `top` must be applied before `next` is pushed
+    fn reduces_before(&self, top: &dyn Operator, next: &dyn Operator) -> bool {
+        if *self == Evaluation::LeftToRight {
+            return true;
+        }
+        let (top_level, next_level) = (self.level(top), self.level(next));
+        top_level > next_level || (top_level == next_level && !next.is_right_associative())
+    }
+}
+
+// Shunting-yard state: operands and operators held back until one binding less tightly arrives.
+// Operators are indices into a slice so that states can be compared and hashed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Pending {
+    values: Vec<Value>,
+    operators: Vec<usize>,
+}
+
+impl Pending {
+    fn new(first: Value) -> Pending {
+        Pending {
+            values: vec![first],
+            operators: vec![],
         }
     }
+
+    fn reduce(&mut self, ops: &[Operation]) -> Option<()> {
+        let op = self.operators.pop()?;
+        let b = self.values.pop()?;
+        let a = self.values.pop()?;
+        self.values.push(ops[op].apply(a, b)?);
+        Some(())
+    }
+
+    fn push(
+        &mut self,
+        ops: &[Operation],
+        op: usize,
+        arg: Value,
+        evaluation: Evaluation,
+    ) -> Option<()> {
+        while let Some(&top) = self.operators.last() {
+            if !evaluation.reduces_before(ops[top].as_ref(), ops[op].as_ref()) {
+                break;
+            }
+            self.reduce(ops)?;
+        }
+        self.operators.push(op);
+        self.values.push(arg);
+        Some(())
+    }
+
+    fn value(mut self, ops: &[Operation]) -> Option<Value> {
+        while !self.operators.is_empty() {
+            self.reduce(ops)?;
+        }
+        self.values.pop()
+    }
+}
+
+// Lazily walks every operator assignment depth-first, yielding those that hit the target
+struct Assignments<'a> {
+    operands: &'a [Value],
+    target: Value,
+    possible_operators: &'a [Operation],
+    evaluation: Evaluation,
+    can_prune: bool,
+    stack: Vec<(Pending, Vec<usize>)>,
+}
+
+impl Iterator for Assignments<'_> {
+    type Item = Expression;
+
+    fn next(&mut self) -> Option<Expression> {
+        let ops = self.possible_operators;
+        while let Some((pending, chosen)) = self.stack.pop() {
+            if chosen.len() + 1 == self.operands.len() {
+                if pending.value(ops) == Some(self.target) {
+                    return Some(Expression {
+                        operands: self.operands.to_vec(),
+                        operators: chosen.iter().map(|&op| ops[op].clone()).collect(),
+                    });
+                }
+                continue;
+            }
+
+            // An undefined prefix only rules out the branch when values cannot decrease again
+            if self.can_prune && pending.clone().value(ops).is_none_or(|v| v > self.target) {
+                continue;
+            }
+
+            // Pushed in reverse so assignments come out in operator order
+            let arg = self.operands[chosen.len() + 1];
+            for op in (0..ops.len()).rev() {
+                let mut next = pending.clone();
+                if next.push(ops, op, arg, self.evaluation).is_none() {
+                    continue;
+                }
+                let mut chosen = chosen.clone();
+                chosen.push(op);
+                self.stack.push((next, chosen));
+            }
+        }
+        None
+    }
 }
 
 #[derive(Debug, Default)]
 struct Expression {
-    operands: Vec<u64>,
+    operands: Vec<Value>,
     operators: Vec<Operation>,
 }
 
 impl Expression {
-    fn from(operands: &[u64], target: u64, possible_operators: &[Operation]) -> Option<Expression> {
+    fn from(
+        operands: &[Value],
+        target: Value,
+        possible_operators: &[Operation],
+        evaluation: Evaluation,
+    ) -> Option<Expression> {
         let expression = Expression {
             operands: vec![operands[0]],
             operators: vec![],
         };
 
+        let can_prune = Expression::can_prune(operands, possible_operators);
+
         let mut candidates = VecDeque::from([expression]);
 
         while let Some(candidate) = candidates.pop_front() {
-            let value = candidate.calculate();
-
-            if value == target {
-                return Some(candidate);
-            }
+            // Operators held back by precedence may still turn an undefined prefix into a
+            // defined whole, so only operators that have already been applied rule it out
+            let Some(pending) = candidate.pending(evaluation) else {
+                continue;
+            };
 
-            // Cannot get smaller value with + and *
-            if value > target {
+            // Every operand must be used, not just a prefix that happens to match
+            if candidate.operands.len() == operands.len() {
+                if pending.value(&candidate.operators) == Some(target) {
+                    return Some(candidate);
+                }
                 continue;
             }
 
-            // No more operands left
-            if candidate.operands.len() == operands.len() {
+            if can_prune
+                && pending
+                    .value(&candidate.operators)
+                    .is_none_or(|v| v > target)
+            {
                 continue;
             }
 
@@ -79,9 +441,190 @@ impl Expression {
         None
     }
 
-    fn calculate(&self) -> u64 {
-        let op_arg_pairs = self.operators.iter().zip(self.operands[1..].iter());
-        op_arg_pairs.fold(self.operands[0], |res, (op, arg)| op.apply(res, *arg))
+    fn unwind(
+        operands: &[Value],
+        target: Value,
+        possible_operators: &[Operation],
+        operators: &mut Vec<Operation>,
+    ) -> bool {
+        let (last, rest) = operands
+            .split_last()
+            .expect("Expression must have operands");
+        if rest.is_empty() {
+            return *last == target;
+        }
+
+        for op in possible_operators {
+            // Any value of the remaining operands will do, as long as there is one
+            if op.absorbs(target, *last) {
+                let mut prefix = vec![];
+                if Expression::evaluate_any(rest[0], &rest[1..], possible_operators, &mut prefix) {
+                    operators.push(op.clone());
+                    operators.extend(prefix.into_iter().rev());
+                    return true;
+                }
+            }
+
+            let Some(previous) = op.unapply(target, *last) else {
+                continue;
+            };
+
+            operators.push(op.clone());
+            if Expression::unwind(rest, previous, possible_operators, operators) {
+                return true;
+            }
+            operators.pop();
+        }
+
+        false
+    }
+
+    fn evaluate_any(
+        value: Value,
+        operands: &[Value],
+        possible_operators: &[Operation],
+        operators: &mut Vec<Operation>,
+    ) -> bool {
+        let Some((next, rest)) = operands.split_first() else {
+            return true;
+        };
+
+        for op in possible_operators {
+            let Some(value) = op.apply(value, *next) else {
+                continue;
+            };
+
+            operators.push(op.clone());
+            if Expression::evaluate_any(value, rest, possible_operators, operators) {
+                return true;
+            }
+            operators.pop();
+        }
+
+        false
+    }
+
+    fn from_target(
+        operands: &[Value],
+        target: Value,
+        possible_operators: &[Operation],
+    ) -> Option<Expression> {
+        let mut operators = vec![];
+        if !Expression::unwind(operands, target, possible_operators, &mut operators) {
+            return None;
+        }
+
+        // Operators are found from the last operand backwards
+        operators.reverse();
+        Some(Expression {
+            operands: operands.to_vec(),
+            operators,
+        })
+    }
+
+    fn pending(&self, evaluation: Evaluation) -> Option<Pending> {
+        let mut pending = Pending::new(self.operands[0]);
+        for (op, arg) in self.operands[1..].iter().enumerate() {
+            pending.push(&self.operators, op, *arg, evaluation)?;
+        }
+        Some(pending)
+    }
+
+    fn calculate(&self, evaluation: Evaluation) -> Option<Value> {
+        self.pending(evaluation)?.value(&self.operators)
+    }
+
+    // Pruning values above the target is only sound if they cannot decrease again
+    fn can_prune(operands: &[Value], possible_operators: &[Operation]) -> bool {
+        possible_operators.iter().all(|op| op.is_monotone()) && !operands[1..].contains(&0)
+    }
+
+    fn all<'a>(
+        operands: &'a [Value],
+        target: Value,
+        possible_operators: &'a [Operation],
+        evaluation: Evaluation,
+    ) -> Assignments<'a> {
+        Assignments {
+            operands,
+            target,
+            possible_operators,
+            evaluation,
+            can_prune: Expression::can_prune(operands, possible_operators),
+            stack: vec![(Pending::new(operands[0]), vec![])],
+        }
+    }
+
+    fn count(
+        operands: &[Value],
+        target: Value,
+        possible_operators: &[Operation],
+        evaluation: Evaluation,
+    ) -> u128 {
+        let can_prune = Expression::can_prune(operands, possible_operators);
+        let mut memo = HashMap::new();
+        Expression::count_from(
+            Pending::new(operands[0]),
+            &operands[1..],
+            target,
+            possible_operators,
+            evaluation,
+            can_prune,
+            &mut memo,
+        )
+    }
+
+    // Different assignments often reach the same state, so counts are shared between them
+    fn count_from(
+        pending: Pending,
+        rest: &[Value],
+        target: Value,
+        possible_operators: &[Operation],
+        evaluation: Evaluation,
+        can_prune: bool,
+        memo: &mut HashMap<(usize, Pending), u128>,
+    ) -> u128 {
+        let Some((next, rest)) = rest.split_first() else {
+            return u128::from(pending.value(possible_operators) == Some(target));
+        };
+
+        if can_prune
+            && pending
+                .clone()
+                .value(possible_operators)
+                .is_none_or(|v| v > target)
+        {
+            return 0;
+        }
+
+        let key = (rest.len(), pending);
+        if let Some(&count) = memo.get(&key) {
+            return count;
+        }
+
+        let mut count = 0;
+        for op in 0..possible_operators.len() {
+            let mut pending = key.1.clone();
+            if pending
+                .push(possible_operators, op, *next, evaluation)
+                .is_none()
+            {
+                continue;
+            }
+            let ways = Expression::count_from(
+                pending,
+                rest,
+                target,
+                possible_operators,
+                evaluation,
+                can_prune,
+                memo,
+            );
+            count = u128::checked_add(count, ways).expect("Too many assignments to count");
+        }
+
+        memo.insert(key, count);
+        count
     }
 }
 
@@ -95,16 +638,72 @@ impl fmt::Display for Expression {
     }
 }
 
-fn read_input(filename: &str) -> (Vec<Vec<u64>>, Vec<u64>) {
+#[derive(Debug, PartialEq)]
+enum ExpressionError {
+    Empty,
+    InvalidOperand(String),
+    UnknownOperator(String),
+    MissingOperand,
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpressionError::Empty => write!(f, "Expression is empty"),
+            ExpressionError::InvalidOperand(text) => write!(f, "'{text}' is not a valid operand"),
+            ExpressionError::UnknownOperator(text) => write!(f, "'{text}' is not a known operator"),
+            ExpressionError::MissingOperand => write!(f, "Expression ends with an operator"),
+        }
+    }
+}
+
+impl FromStr for Expression {
+    type Err = ExpressionError;
+
+    // Reads the form written by Display, with operands and operators separated by spaces
+    fn from_str(text: &str) -> Result<Expression, ExpressionError> {
+        let mut tokens = text.split_whitespace();
+        let parse_operand = |token: &str| {
+            token
+                .parse()
+                .map_err(|_| ExpressionError::InvalidOperand(token.to_string()))
+        };
+
+        let first = tokens.next().ok_or(ExpressionError::Empty)?;
+        let mut expression = Expression {
+            operands: vec![parse_operand(first)?],
+            operators: vec![],
+        };
+
+        while let Some(symbol) = tokens.next() {
+            let op = get_operator(symbol)
+                .ok_or_else(|| ExpressionError::UnknownOperator(symbol.to_string()))?;
+            let operand = tokens.next().ok_or(ExpressionError::MissingOperand)?;
+            expression.operators.push(op);
+            expression.operands.push(parse_operand(operand)?);
+        }
+
+        Ok(expression)
+    }
+}
+
+fn read_input(filename: &str) -> (Vec<Vec<Value>>, Vec<Value>) {
     let contents = fs::read_to_string(filename).expect("Unable to read input file");
+    parse_input(&contents)
+}
+
+fn parse_input(contents: &str) -> (Vec<Vec<Value>>, Vec<Value>) {
     let mut operands = vec![];
     let mut targets = vec![];
     for line in contents.trim().split("\n") {
         if let Some((target, expression_operands)) = line.split_once(":") {
-            let target: u64 = target.trim().parse().expect("invalid target value");
+            let target: Value = target
+                .trim()
+                .parse()
+                .expect("invalid target value, large targets need the wide feature");
             targets.push(target);
 
-            let expression_operands: Vec<u64> = expression_operands
+            let expression_operands: Vec<Value> = expression_operands
                 .trim()
                 .split(" ")
                 .map(|v| v.parse().expect("invalid operand"))
@@ -115,26 +714,553 @@ fn read_input(filename: &str) -> (Vec<Vec<u64>>, Vec<u64>) {
     (operands, targets)
 }
 
-fn solve(operands: &[Vec<u64>], targets: &[u64], possible_operators: &[Operation]) -> u64 {
-    let mut sum = 0;
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Solver {
+    Bfs,
+    Backward,
+}
+
+impl Solver {
+    // Unwinding relies on left-to-right order and on every operator having an inverse
+    fn is_available(&self, ops: &[Operation], evaluation: Evaluation) -> bool {
+        match self {
+            Solver::Bfs => true,
+            Solver::Backward => {
+                evaluation == Evaluation::LeftToRight && ops.iter().all(|op| op.is_invertible())
+            }
+        }
+    }
+
+    fn find(
+        &self,
+        operands: &[Value],
+        target: Value,
+        ops: &[Operation],
+        evaluation: Evaluation,
+    ) -> Option<Expression> {
+        match self {
+            Solver::Backward if self.is_available(ops, evaluation) => {
+                Expression::from_target(operands, target, ops)
+            }
+            Solver::Bfs | Solver::Backward => Expression::from(operands, target, ops, evaluation),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Outcome {
+    target: Value,
+    expression: Option<Expression>,
+}
+
+impl Outcome {
+    fn to_json(&self) -> String {
+        match &self.expression {
+            Some(expression) => format!(
+                "{{\"target\":{},\"expression\":\"{expression}\"}}",
+                self.target
+            ),
+            None => format!("{{\"target\":{},\"expression\":null}}", self.target),
+        }
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.expression {
+            Some(expression) => write!(f, "{} = {expression}", self.target),
+            None => write!(f, "{}: no solution", self.target),
+        }
+    }
+}
+
+fn solve(
+    operands: &[Vec<Value>],
+    targets: &[Value],
+    possible_operators: &[Operation],
+    solver: Solver,
+    evaluation: Evaluation,
+    num_threads: usize,
+) -> Vec<Outcome> {
+    let lines = operands.iter().zip(targets.iter()).collect::<Vec<_>>();
+    let chunk_size = lines.len().div_ceil(num_threads.max(1)).max(1);
+
+    thread::scope(|scope| {
+        let workers = lines
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|(expression_operands, target)| Outcome {
+                            target: **target,
+                            expression: solver.find(
+                                expression_operands,
+                                **target,
+                                possible_operators,
+                                evaluation,
+                            ),
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Joining in spawn order keeps the results in input order
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Worker thread panicked"))
+            .collect()
+    })
+}
+
+// Many targets close to the limit of a Value can still add up past it
+#[cfg_attr(feature = "wide", allow(clippy::useless_conversion))]
+fn total(outcomes: &[Outcome]) -> u128 {
+    outcomes
+        .iter()
+        .filter(|outcome| outcome.expression.is_some())
+        .map(|outcome| u128::from(outcome.target))
+        .sum()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Quiet,
+    Human,
+    Json,
+    Csv,
+}
+
+fn report(results: &[(&str, Vec<Outcome>)], format: OutputFormat) -> String {
+    let answers = results
+        .iter()
+        .map(|(label, outcomes)| format!("{label} answer is: {}", total(outcomes)));
+
+    match format {
+        OutputFormat::Quiet => answers.collect::<Vec<_>>().join("\n"),
+        OutputFormat::Human => results
+            .iter()
+            .zip(answers)
+            .map(|((_, outcomes), answer)| {
+                let lines = outcomes.iter().map(|outcome| format!("{outcome}\n"));
+                lines.collect::<String>() + &answer
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        OutputFormat::Json => {
+            let sets = results.iter().map(|(label, outcomes)| {
+                let entries = outcomes.iter().map(Outcome::to_json).collect::<Vec<_>>();
+                format!(
+                    "{{\"label\":\"{label}\",\"answer\":{},\"results\":[{}]}}",
+                    total(outcomes),
+                    entries.join(",")
+                )
+            });
+            format!("[{}]", sets.collect::<Vec<_>>().join(","))
+        }
+        OutputFormat::Csv => {
+            let rows = results.iter().flat_map(|(label, outcomes)| {
+                outcomes
+                    .iter()
+                    .map(move |outcome| match &outcome.expression {
+                        Some(expression) => format!("{label},{},{expression}", outcome.target),
+                        None => format!("{label},{},", outcome.target),
+                    })
+            });
+            let mut lines = vec!["operators,target,expression".to_string()];
+            lines.extend(rows);
+            lines.join("\n")
+        }
+    }
+}
+
+fn compare(
+    operands: &[Vec<Value>],
+    targets: &[Value],
+    possible_operators: &[Operation],
+    evaluation: Evaluation,
+) -> usize {
+    let mut mismatches = 0;
     for (expression_operands, target) in operands.iter().zip(targets.iter()) {
-        if let Some(expression) = Expression::from(expression_operands, *target, possible_operators)
-        {
-            println!("{expression} = {target}");
-            sum += target;
+        let results = [Solver::Bfs, Solver::Backward].map(|solver| {
+            solver.find(expression_operands, *target, possible_operators, evaluation)
+        });
+
+        let agree = match &results {
+            [Some(bfs), Some(backward)] => {
+                bfs.calculate(evaluation) == Some(*target)
+                    && backward.calculate(evaluation) == Some(*target)
+            }
+            [None, None] => true,
+            _ => false,
+        };
+
+        if !agree {
+            let [bfs, backward] = results.map(|result| match result {
+                Some(expression) => expression.to_string(),
+                None => "none".to_string(),
+            });
+            println!("{target}: bfs found {bfs}, backward found {backward}");
+            mismatches += 1;
+        }
+    }
+    mismatches
+}
+
+fn count_assignments(
+    operands: &[Vec<Value>],
+    targets: &[Value],
+    possible_operators: &[Operation],
+    evaluation: Evaluation,
+) -> u128 {
+    let mut total = 0;
+    for (expression_operands, target) in operands.iter().zip(targets.iter()) {
+        let count = Expression::count(expression_operands, *target, possible_operators, evaluation);
+        if count > 0 {
+            println!("{target}: {count}");
+        }
+        total = u128::checked_add(total, count).expect("Too many assignments to count");
+    }
+    total
+}
+
+#[derive(Debug, PartialEq)]
+enum VerifyError {
+    MissingEquals,
+    InvalidTarget(String),
+    Expression(ExpressionError),
+    WrongValue {
+        expression: String,
+        value: Option<Value>,
+        target: Value,
+    },
+    NotInInput(String),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::MissingEquals => write!(f, "Expected 'target = expression'"),
+            VerifyError::InvalidTarget(text) => write!(f, "'{text}' is not a valid target"),
+            VerifyError::Expression(err) => write!(f, "{err}"),
+            VerifyError::WrongValue {
+                expression,
+                value: Some(value),
+                target,
+            } => write!(f, "{expression} is {value}, not {target}"),
+            VerifyError::WrongValue {
+                expression, target, ..
+            } => write!(f, "{expression} is undefined, not {target}"),
+            VerifyError::NotInInput(equation) => {
+                write!(f, "{equation} does not use the operands of any input line")
+            }
+        }
+    }
+}
+
+// Accepts the target on either side of the equals sign
+fn verify_line(
+    line: &str,
+    operands: &[Vec<Value>],
+    targets: &[Value],
+    evaluation: Evaluation,
+) -> Result<(), VerifyError> {
+    let (left, right) = line.split_once("=").ok_or(VerifyError::MissingEquals)?;
+    let (target, expression) = match left.trim().parse::<Value>() {
+        Ok(target) => (target, right),
+        Err(_) => {
+            let target = right
+                .trim()
+                .parse()
+                .map_err(|_| VerifyError::InvalidTarget(left.trim().to_string()))?;
+            (target, left)
+        }
+    };
+    let expression: Expression = expression.parse().map_err(VerifyError::Expression)?;
+
+    let value = expression.calculate(evaluation);
+    if value != Some(target) {
+        return Err(VerifyError::WrongValue {
+            expression: expression.to_string(),
+            value,
+            target,
+        });
+    }
+
+    let in_input = operands
+        .iter()
+        .zip(targets.iter())
+        .any(|(line_operands, line_target)| {
+            *line_target == target && *line_operands == expression.operands
+        });
+    if !in_input {
+        return Err(VerifyError::NotInInput(format!("{target} = {expression}")));
+    }
+
+    Ok(())
+}
+
+fn verify(
+    contents: &str,
+    operands: &[Vec<Value>],
+    targets: &[Value],
+    evaluation: Evaluation,
+) -> usize {
+    let mut failures = 0;
+    for (number, line) in contents.lines().enumerate() {
+        // Human-readable solve output also lists unsolved targets and the answers
+        let line = line.trim();
+        if line.is_empty() || line.ends_with(": no solution") || line.contains(" answer is: ") {
+            continue;
+        }
+        if let Err(err) = verify_line(line, operands, targets, evaluation) {
+            println!("Line {}: {err}", number + 1);
+            failures += 1;
         }
     }
-    sum
+    failures
+}
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn between(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next() % (high - low + 1)
+    }
+}
+
+// Roughly half of the lines are built from a random assignment, so that they are solvable
+fn generate_input(num_lines: usize, possible_operators: &[Operation], seed: u64) -> String {
+    let mut rng = XorShift(seed.max(1));
+    let mut lines = vec![];
+    while lines.len() < num_lines {
+        let num_operands = rng.between(3, 9) as usize;
+        let operands = (0..num_operands)
+            .map(|_| rng.between(1, 99) as Value)
+            .collect::<Vec<_>>();
+
+        let target = if rng.next().is_multiple_of(2) {
+            let operators = (1..num_operands)
+                .map(|_| possible_operators[rng.next() as usize % possible_operators.len()].clone())
+                .collect();
+            let expression = Expression {
+                operands: operands.clone(),
+                operators,
+            };
+            match expression.calculate(Evaluation::LeftToRight) {
+                Some(target) => target,
+                None => continue,
+            }
+        } else {
+            rng.next() as Value % 1_000_000_000
+        };
+
+        let operands = operands.iter().map(Value::to_string).collect::<Vec<_>>();
+        lines.push(format!("{target}: {}", operands.join(" ")));
+    }
+    lines.join("\n")
+}
+
+fn bench(sizes: &[usize], num_threads: usize) {
+    let operators: Vec<Operation> = vec![Arc::new(Add), Arc::new(Multiply), Arc::new(Concatenate)];
+    for &num_lines in sizes {
+        let contents = generate_input(num_lines, &operators, num_lines as u64);
+        let (operands, targets) = parse_input(&contents);
+
+        let start = Instant::now();
+        let expected = solve(
+            &operands,
+            &targets,
+            &operators,
+            Solver::Bfs,
+            Evaluation::LeftToRight,
+            1,
+        );
+        let sequential = start.elapsed();
+
+        let start = Instant::now();
+        let outcomes = solve(
+            &operands,
+            &targets,
+            &operators,
+            Solver::Bfs,
+            Evaluation::LeftToRight,
+            num_threads,
+        );
+        let parallel = start.elapsed();
+
+        let same_order = expected
+            .iter()
+            .zip(outcomes.iter())
+            .all(|(a, b)| a.to_string() == b.to_string());
+        assert!(same_order, "Parallel results differ from sequential ones");
+        assert_eq!(total(&expected), total(&outcomes));
+        println!(
+            "{num_lines} lines: answer {}, sequential {sequential:?}, {num_threads} threads {parallel:?}",
+            total(&outcomes)
+        );
+    }
 }
 
 fn main() {
+    let mut solver = Solver::Bfs;
+    let mut evaluation = Evaluation::LeftToRight;
+    let mut output = OutputFormat::Quiet;
+    let mut num_threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let mut custom_operators = None;
+    let mut args = vec![];
+    for arg in env::args().skip(1) {
+        if let Some(symbols) = arg.strip_prefix("--operators=") {
+            let operators = symbols
+                .split(",")
+                .map(get_operator)
+                .collect::<Option<Vec<_>>>();
+            match operators {
+                Some(operators) => custom_operators = Some(operators),
+                None => {
+                    println!("Operators must be a comma separated list of + * | - / ** ^");
+                    return;
+                }
+            }
+            continue;
+        }
+
+        if let Some(threads) = arg.strip_prefix("--threads=") {
+            match threads.parse() {
+                Ok(threads) if threads > 0 => num_threads = threads,
+                _ => {
+                    println!("Thread count must be a positive number");
+                    return;
+                }
+            }
+            continue;
+        }
+
+        if let Some(level) = arg.strip_prefix("--concat-precedence=") {
+            let Ok(concatenation) = level.parse() else {
+                println!("Concatenation precedence must be a number, e.g. 1 for + or 2 for *");
+                return;
+            };
+            evaluation = Evaluation::Precedence { concatenation };
+            continue;
+        }
+
+        match arg.as_str() {
+            "--solver=bfs" => solver = Solver::Bfs,
+            "--solver=backward" => solver = Solver::Backward,
+            "--output=quiet" => output = OutputFormat::Quiet,
+            "--output=human" => output = OutputFormat::Human,
+            "--output=json" => output = OutputFormat::Json,
+            "--output=csv" => output = OutputFormat::Csv,
+            "--evaluation=left-to-right" => evaluation = Evaluation::LeftToRight,
+            "--evaluation=precedence" => {
+                // Concatenation binds tightest by default, like writing the digits together
+                if evaluation == Evaluation::LeftToRight {
+                    evaluation = Evaluation::Precedence { concatenation: 4 };
+                }
+            }
+            _ => args.push(arg),
+        }
+    }
+
+    if args.first().map(String::as_str) == Some("bench") {
+        let sizes = match args.get(1).and_then(|arg| arg.parse().ok()) {
+            Some(size) => vec![size],
+            None => vec![1_000, 5_000, 20_000],
+        };
+        bench(&sizes, num_threads);
+        return;
+    }
+
     let (operands, targets) = read_input("input.txt");
 
-    let mut allowed_operators = vec![Operation::Add, Operation::Multiply];
-    let answer = solve(&operands, &targets, &allowed_operators);
-    println!("Part 1 answer is: {answer}");
+    let operator_sets: Vec<(&str, Vec<Operation>)> = match custom_operators {
+        Some(operators) => vec![("Custom", operators)],
+        None => vec![
+            ("Part 1", vec![Arc::new(Add), Arc::new(Multiply)]),
+            (
+                "Part 2",
+                vec![Arc::new(Add), Arc::new(Multiply), Arc::new(Concatenate)],
+            ),
+        ],
+    };
+
+    if args.first().map(String::as_str) == Some("compare") {
+        for (label, operators) in &operator_sets {
+            // Otherwise both sides would be the forward search
+            if !Solver::Backward.is_available(operators, evaluation) {
+                println!("{label}: backward solver unavailable for these operators, skipped");
+                continue;
+            }
+            let mismatches = compare(&operands, &targets, operators, evaluation);
+            println!("{label}: solvers disagree on {mismatches} equations");
+        }
+        return;
+    }
+
+    if args.first().map(String::as_str) == Some("verify") {
+        let Some(filename) = args.get(1) else {
+            println!("Usage: verify <file>");
+            return;
+        };
+        let Ok(contents) = fs::read_to_string(filename) else {
+            println!("Unable to read {filename}");
+            return;
+        };
+        let failures = verify(&contents, &operands, &targets, evaluation);
+        println!("{failures} equations failed verification");
+        return;
+    }
+
+    if args.first().map(String::as_str) == Some("count") {
+        for (label, operators) in &operator_sets {
+            let total = count_assignments(&operands, &targets, operators, evaluation);
+            println!("{label}: {total} assignments");
+        }
+        return;
+    }
+
+    if args.first().map(String::as_str) == Some("all") {
+        let Some(line) = args.get(1).and_then(|arg| arg.parse::<usize>().ok()) else {
+            println!("Usage: all <line>");
+            return;
+        };
+        let Some((expression_operands, target)) = operands.get(line).zip(targets.get(line)) else {
+            println!("Input only has {} lines", targets.len());
+            return;
+        };
+        for (label, operators) in &operator_sets {
+            println!("{label}:");
+            for expression in Expression::all(expression_operands, *target, operators, evaluation) {
+                println!("{target} = {expression}");
+            }
+        }
+        return;
+    }
 
-    allowed_operators.push(Operation::Concatenate);
-    let answer = solve(&operands, &targets, &allowed_operators);
-    println!("Part 2 answer is: {answer}");
+    let results: Vec<(&str, Vec<Outcome>)> = operator_sets
+        .iter()
+        .map(|(label, operators)| {
+            (
+                *label,
+                solve(
+                    &operands,
+                    &targets,
+                    operators,
+                    solver,
+                    evaluation,
+                    num_threads,
+                ),
+            )
+        })
+        .collect();
+    println!("{}", report(&results, output));
 }
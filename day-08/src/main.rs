@@ -1,10 +1,11 @@
-use std::collections::HashSet;
-use std::fs;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::ops::{Add, Sub};
+use std::{env, fs};
 use gcd::binary_u32;
 
 
-#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq, Ord, PartialOrd)]
 struct Position {
     row: i32,
     col: i32,
@@ -87,53 +88,122 @@ fn read_input(filename: &str) -> Map {
     }
 }
 
-fn get_antinodes_in_pair(a: &Antena, b: &Antena, map: &Map) -> Vec<Position> {
-    let delta = b.position - &a.position;
+// Describes which points on the line through two antennae are antinodes
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Resonance {
+    // Distance ratio p:q to the two antennae, or None for every point on the line
+    ratio: Option<(u32, u32)>,
+    // Whether points strictly between the antennae count
+    interior: bool,
+    // How many antenna distances beyond either antenna to look, if limited
+    max_harmonic: Option<u32>,
+}
 
-    let mut antinodes = vec![];
+impl Resonance {
+    const PAIR: Resonance = Resonance {
+        ratio: Some((1, 2)),
+        interior: false,
+        max_harmonic: None,
+    };
+
+    const LINE: Resonance = Resonance {
+        ratio: None,
+        interior: true,
+        max_harmonic: None,
+    };
+
+    // `steps` counts lattice steps from `a`, where `b` is `span` steps away
+    fn is_antinode(&self, steps: i32, span: i32) -> bool {
+        if !self.interior && 0 < steps && steps < span {
+            return false;
+        }
 
-    let antinode_a = a.position - &delta;
-    if map.contains(&antinode_a) {
-        antinodes.push(antinode_a);
-    }
+        let beyond = if steps < 0 { -steps } else { steps - span };
+        let harmonic = (beyond.max(0) as u32).div_ceil(span as u32);
+        if self.max_harmonic.is_some_and(|max| harmonic > max) {
+            return false;
+        }
 
-    let antinode_b = b.position + &delta;
-    if map.contains(&antinode_b) {
-        antinodes.push(antinode_b);
+        let Some((p, q)) = self.ratio else {
+            return true;
+        };
+        // Both factors fit in a u32, so their products cannot overflow a u64
+        let (p, q) = (u64::from(p), u64::from(q));
+        let (to_a, to_b) = (
+            u64::from(steps.unsigned_abs()),
+            u64::from((steps - span).unsigned_abs()),
+        );
+        to_a * q == to_b * p || to_a * p == to_b * q
     }
 
-    antinodes
-}
+    fn antinodes(&self, a: &Antena, b: &Antena, map: &Map) -> Vec<Position> {
+        let mut delta = b.position - &a.position;
+        let span = binary_u32(delta.row.unsigned_abs(), delta.col.unsigned_abs()) as i32;
+        delta.simplify();
+
+        let mut antinodes = vec![];
 
-fn get_antinodes_in_line(a: &Antena, b: &Antena, map: &Map) -> Vec<Position> {
-    let mut delta = b.position - &a.position;
-    delta.simplify();
+        let mut steps = 0;
+        let mut antinode = a.position;
+        while map.contains(&antinode) {
+            if self.is_antinode(steps, span) {
+                antinodes.push(antinode);
+            }
+            steps -= 1;
+            antinode = antinode - &delta;
+        }
 
-    let mut antinodes = vec![];
+        steps = 1;
+        antinode = a.position + &delta;
+        while map.contains(&antinode) {
+            if self.is_antinode(steps, span) {
+                antinodes.push(antinode);
+            }
+            steps += 1;
+            antinode = antinode + &delta;
+        }
 
-    let mut antinode = a.position;
-    while map.contains(&antinode) {
-        antinodes.push(antinode);
-        antinode = antinode - &delta;
+        antinodes
     }
+}
 
-    antinode = a.position + &delta;
-    while map.contains(&antinode) {
-        antinodes.push(antinode);
-        antinode = antinode + &delta;
+// An antenna pair that produces a given antinode
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Source {
+    kind: char,
+    a: Position,
+    b: Position,
+}
+
+impl Source {
+    fn to_json(self) -> String {
+        format!(
+            "{{\"kind\":\"{}\",\"a\":[{},{}],\"b\":[{},{}]}}",
+            self.kind, self.a.row, self.a.col, self.b.row, self.b.col
+        )
     }
+}
 
-    antinodes
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at ({}, {}) and ({}, {})",
+            self.kind, self.a.row, self.a.col, self.b.row, self.b.col
+        )
+    }
 }
 
-fn solve(map: &Map, generate_antinodes: fn(&Antena, &Antena, &Map) -> Vec<Position>) -> usize {
+type AntinodeIndex = BTreeMap<Position, Vec<Source>>;
+
+fn index_antinodes(map: &Map, resonance: &Resonance) -> AntinodeIndex {
     let kinds = map
         .antennae
         .iter()
         .map(|antena| antena.kind)
-        .collect::<HashSet<_>>();
+        .collect::<BTreeSet<_>>();
 
-    let mut antinodes = HashSet::new();
+    let mut index = AntinodeIndex::new();
 
     for kind in kinds {
         let mut antennae = map
@@ -152,20 +222,172 @@ fn solve(map: &Map, generate_antinodes: fn(&Antena, &Antena, &Map) -> Vec<Positi
 
         for i in 0..antennae.len() {
             for j in i + 1..antennae.len() {
-                antinodes.extend(generate_antinodes(antennae[i], antennae[j], map));
+                let (a, b) = (antennae[i], antennae[j]);
+                let source = Source {
+                    kind,
+                    a: a.position,
+                    b: b.position,
+                };
+                for antinode in resonance.antinodes(a, b, map) {
+                    index.entry(antinode).or_default().push(source);
+                }
+            }
+        }
+    }
+
+    index
+}
+
+fn solve(map: &Map, resonance: &Resonance) -> usize {
+    index_antinodes(map, resonance).len()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Json,
+    Csv,
+}
+
+fn export(indices: &[(&str, AntinodeIndex)], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Json => {
+            let entries = indices
+                .iter()
+                .map(|(label, index)| {
+                    let antinodes = index
+                        .iter()
+                        .map(|(position, sources)| {
+                            let sources = sources.iter().map(|source| source.to_json());
+                            format!(
+                                "{{\"row\":{},\"col\":{},\"sources\":[{}]}}",
+                                position.row,
+                                position.col,
+                                sources.collect::<Vec<_>>().join(",")
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    format!(
+                        "{{\"resonance\":\"{label}\",\"antinodes\":[{}]}}",
+                        antinodes.join(",")
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!("[{}]", entries.join(","))
+        }
+        ExportFormat::Csv => {
+            let mut lines = vec!["resonance,row,col,kind,a_row,a_col,b_row,b_col".to_string()];
+            for (label, index) in indices {
+                for (position, sources) in index {
+                    for source in sources {
+                        lines.push(format!(
+                            "{label},{},{},{},{},{},{},{}",
+                            position.row,
+                            position.col,
+                            source.kind,
+                            source.a.row,
+                            source.a.col,
+                            source.b.row,
+                            source.b.col
+                        ));
+                    }
+                }
             }
+            lines.join("\n")
         }
     }
+}
 
-    antinodes.len()
+fn parse_ratio(text: &str) -> Option<(u32, u32)> {
+    let (p, q) = text.split_once(":")?;
+    let (p, q) = (p.parse().ok()?, q.parse().ok()?);
+    (p > 0 && q > 0).then_some((p, q))
 }
 
 fn main() {
+    let mut custom = None;
+    let mut args = vec![];
+    for arg in env::args().skip(1) {
+        if !arg.starts_with("--") {
+            args.push(arg);
+            continue;
+        }
+
+        let resonance = custom.get_or_insert(Resonance::PAIR);
+        if arg == "--interior" {
+            resonance.interior = true;
+        } else if arg == "--ratio=all" {
+            resonance.ratio = None;
+        } else if let Some(ratio) = arg.strip_prefix("--ratio=") {
+            let Some(ratio) = parse_ratio(ratio) else {
+                println!("Ratio must be two positive numbers such as 1:2, or all");
+                return;
+            };
+            resonance.ratio = Some(ratio);
+        } else if let Some(max) = arg.strip_prefix("--max-harmonic=") {
+            let Ok(max) = max.parse() else {
+                println!("Maximum harmonic must be a number");
+                return;
+            };
+            resonance.max_harmonic = Some(max);
+        } else {
+            println!("Unknown argument {arg}");
+            return;
+        }
+    }
+
     let map = read_input("input.txt");
 
-    let answer = solve(&map, get_antinodes_in_pair);
-    println!("Part 1 answer is: {answer}");
+    let resonances = match custom {
+        Some(resonance) => vec![("Custom", resonance)],
+        None => vec![("Part 1", Resonance::PAIR), ("Part 2", Resonance::LINE)],
+    };
+
+    match args.first().map(String::as_str) {
+        Some("explain") => {
+            let coordinates = args.get(1..3).map(|coordinates| {
+                coordinates
+                    .iter()
+                    .map(|coordinate| coordinate.parse::<i32>())
+                    .collect::<Result<Vec<_>, _>>()
+            });
+            let Some(Ok(coordinates)) = coordinates else {
+                println!("Usage: explain <row> <col>");
+                return;
+            };
+            let position = Position {
+                row: coordinates[0],
+                col: coordinates[1],
+            };
 
-    let answer = solve(&map, get_antinodes_in_line);
-    println!("Part 2 answer is: {answer}");
+            for (label, resonance) in &resonances {
+                let index = index_antinodes(&map, resonance);
+                match index.get(&position) {
+                    Some(sources) => {
+                        println!("{label}: antinode produced by");
+                        for source in sources {
+                            println!("  {source}");
+                        }
+                    }
+                    None => println!("{label}: not an antinode"),
+                }
+            }
+        }
+        Some("export") => {
+            let format = match args.get(1).map(String::as_str) {
+                Some("csv") => ExportFormat::Csv,
+                _ => ExportFormat::Json,
+            };
+            let indices = resonances
+                .iter()
+                .map(|(label, resonance)| (*label, index_antinodes(&map, resonance)))
+                .collect::<Vec<_>>();
+            println!("{}", export(&indices, format));
+        }
+        _ => {
+            for (label, resonance) in &resonances {
+                let answer = solve(&map, resonance);
+                println!("{label} answer is: {answer}");
+            }
+        }
+    }
 }